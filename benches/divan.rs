@@ -93,3 +93,116 @@ fn savgol_f32() -> f32 {
     sav_gol_f32::<2, 2>(bb(&mut buf), bb(&v));
     buf[0]
 }
+#[cfg(feature = "half")]
+#[divan::bench(sample_size = 3, sample_count = 3)]
+fn savgol_f16() -> staged_sg_filter::half::f16 {
+    use staged_sg_filter::half::{f16, sav_gol_f16};
+    let n = 100_000_000;
+    let v = vec![f16::from_f32(10.0); n];
+    let mut buf = vec![f16::ZERO; n];
+    sav_gol_f16::<2, 2>(bb(&mut buf), bb(&v));
+    buf[0]
+}
+#[divan::bench(sample_size = 3, sample_count = 3)]
+fn savgol_dot2() -> f64 {
+    use staged_sg_filter::compensated::sav_gol_dot2;
+    let n = 100_000_000;
+    let v = vec![10.0; n];
+    let mut buf = vec![0.0; n];
+    sav_gol_dot2::<2, 2>(bb(&mut buf), bb(&v));
+    buf[0]
+}
+#[divan::bench(sample_size = 3, sample_count = 3)]
+fn savgol_mixed() -> f32 {
+    use staged_sg_filter::sav_gol_mixed;
+    let n = 100_000_000;
+    let v = vec![10.0f32; n];
+    let mut buf = vec![0.0f32; n];
+    sav_gol_mixed::<2, 2>(bb(&mut buf), bb(&v));
+    buf[0]
+}
+#[divan::bench(sample_size = 3, sample_count = 3)]
+fn savgol_f32_wide() -> f32 {
+    let n = 10_000_000;
+    let v = vec![10.0f32; n];
+    let mut buf = vec![0.0f32; n];
+    sav_gol_f32::<25, 4>(bb(&mut buf), bb(&v));
+    buf[0]
+}
+#[divan::bench(sample_size = 3, sample_count = 3)]
+fn savgol_mixed_wide() -> f32 {
+    use staged_sg_filter::sav_gol_mixed;
+    let n = 10_000_000;
+    let v = vec![10.0f32; n];
+    let mut buf = vec![0.0f32; n];
+    sav_gol_mixed::<25, 4>(bb(&mut buf), bb(&v));
+    buf[0]
+}
+// The crossover of `fft::CROSSOVER`: the direct kernel against the FFT past the const generic
+// half-widths
+#[divan::bench(args = [20, 25, 26, 50, 100, 400], sample_size = 3, sample_count = 3)]
+fn savgol_dyn_direct(half_width: usize) -> f64 {
+    use staged_sg_filter::{coeffs::fit_coeffs, sav_gol_direct};
+    let coeffs = fit_coeffs(half_width, 3, 0, 0.0);
+    let n = 1_000_000;
+    let v = vec![10.0; n];
+    let mut buf = vec![0.0; n];
+    sav_gol_direct(bb(&mut buf), bb(&v), &coeffs);
+    buf[n / 2]
+}
+#[divan::bench(args = [20, 25, 26, 50, 100, 400], sample_size = 3, sample_count = 3)]
+fn savgol_dyn_fft(half_width: usize) -> f64 {
+    use staged_sg_filter::{coeffs::fit_coeffs, fft::sav_gol_fft};
+    let coeffs = fit_coeffs(half_width, 3, 0, 0.0);
+    let n = 1_000_000;
+    let v = vec![10.0; n];
+    let mut buf = vec![0.0; n];
+    sav_gol_fft(bb(&mut buf), bb(&v), &coeffs);
+    buf[n / 2]
+}
+// Constant time per sample, against the FFT at the same widths
+#[divan::bench(args = [26, 100, 400], sample_size = 3, sample_count = 3)]
+fn savgol_sliding(half_width: usize) -> f64 {
+    use staged_sg_filter::sliding::SlidingSavGol;
+    let n = 1_000_000;
+    let v = vec![10.0; n];
+    let mut buf = vec![0.0; n];
+    let mut filter = SlidingSavGol::new(half_width, 3, 0).unwrap();
+    filter.apply(bb(&mut buf), bb(&v));
+    buf[n / 2]
+}
+// The folded kernel against the full dot product over the window sizes of `COEFFS`
+#[divan::bench(consts = [2, 5, 12, 25], sample_size = 3, sample_count = 3)]
+fn savgol_window<const WINDOW: usize>() -> f64 {
+    let n = 10_000_000;
+    let v = vec![10.0; n];
+    let mut buf = vec![0.0; n];
+    sav_gol::<WINDOW, 4>(bb(&mut buf), bb(&v));
+    buf[n / 2]
+}
+#[divan::bench(consts = [2, 5, 12, 25], sample_size = 3, sample_count = 3)]
+fn savgol_folded<const WINDOW: usize>() -> f64 {
+    use staged_sg_filter::folded::sav_gol_folded;
+    let n = 10_000_000;
+    let v = vec![10.0; n];
+    let mut buf = vec![0.0; n];
+    sav_gol_folded::<WINDOW, 4>(bb(&mut buf), bb(&v));
+    buf[n / 2]
+}
+#[divan::bench(consts = [2, 5, 12, 25], sample_size = 3, sample_count = 3)]
+fn savgol_folded_f32<const WINDOW: usize>() -> f32 {
+    use staged_sg_filter::folded::sav_gol_folded_f32;
+    let n = 10_000_000;
+    let v = vec![10.0f32; n];
+    let mut buf = vec![0.0f32; n];
+    sav_gol_folded_f32::<WINDOW, 4>(bb(&mut buf), bb(&v));
+    buf[n / 2]
+}
+#[divan::bench(consts = [2, 5, 12, 25], sample_size = 3, sample_count = 3)]
+fn savgol_window_f32<const WINDOW: usize>() -> f32 {
+    let n = 10_000_000;
+    let v = vec![10.0f32; n];
+    let mut buf = vec![0.0f32; n];
+    sav_gol_f32::<WINDOW, 4>(bb(&mut buf), bb(&v));
+    buf[n / 2]
+}
@@ -0,0 +1,5 @@
+//! Shared and static library of the C ABI in `staged_sg_filter::ffi`, see
+//! `include/staged_sg_filter.h`.
+
+#[cfg(feature = "std")]
+pub use sgf::ffi::*;
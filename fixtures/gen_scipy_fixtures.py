@@ -0,0 +1,132 @@
+"""Generates `savgol_reference.txt`, reference outputs for `scipy.signal.savgol_filter`.
+
+With SciPy installed the outputs come from SciPy itself. Without it they are computed in exact
+rational arithmetic from the definitions SciPy implements: a least squares polynomial fit over
+each window, `scipy.ndimage` padding for the `mirror`, `nearest`, `constant` and `wrap` modes,
+and a fit to the first and last windows for `interp`, all scaled by `delta ** -deriv`. The
+header of the output says which of the two produced it, with the SciPy version.
+
+    python3 fixtures/gen_scipy_fixtures.py > fixtures/savgol_reference.txt
+"""
+
+from fractions import Fraction
+from math import factorial
+
+try:
+    import numpy as np
+    import scipy
+    from scipy.signal import savgol_filter as scipy_savgol_filter
+except ImportError:
+    scipy_savgol_filter = None
+
+
+def solve(a, b):
+    """Solves `a x = b` exactly by Gauss-Jordan elimination"""
+    n = len(a)
+    m = [row[:] + [rhs] for row, rhs in zip(a, b)]
+    for col in range(n):
+        pivot = next(r for r in range(col, n) if m[r][col] != 0)
+        m[col], m[pivot] = m[pivot], m[col]
+        m[col] = [v / m[col][col] for v in m[col]]
+        for r in range(n):
+            if r != col and m[r][col] != 0:
+                f = m[r][col]
+                m[r] = [v - f * p for v, p in zip(m[r], m[col])]
+    return [row[-1] for row in m]
+
+
+def polyfit(ts, ys, order):
+    """Least squares polynomial coefficients, lowest power first"""
+    ata = [[sum(Fraction(t) ** (i + j) for t in ts) for j in range(order + 1)] for i in range(order + 1)]
+    aty = [sum(Fraction(t) ** i * y for t, y in zip(ts, ys)) for i in range(order + 1)]
+    return solve(ata, aty)
+
+
+def polyder_at(p, deriv, t):
+    return sum(
+        c * Fraction(factorial(k), factorial(k - deriv)) * Fraction(t) ** (k - deriv)
+        for k, c in enumerate(p)
+        if k >= deriv
+    )
+
+
+def fit_at(ys, ts, order, deriv, t):
+    return polyder_at(polyfit(ts, ys, order), deriv, t)
+
+
+def pad(x, i, mode, cval):
+    n = len(x)
+    if 0 <= i < n:
+        return x[i]
+    if mode == "mirror":
+        return x[-i] if i < 0 else x[2 * (n - 1) - i]
+    if mode == "nearest":
+        return x[min(max(i, 0), n - 1)]
+    if mode == "constant":
+        return cval
+    if mode == "wrap":
+        return x[i % n]
+    raise ValueError(mode)
+
+
+def exact_savgol_filter(x, window_length, polyorder, deriv, delta, mode, cval):
+    x = [Fraction(v) for v in x]
+    n, hw = len(x), window_length // 2
+    ts = list(range(-hw, hw + 1))
+    scale = Fraction(delta) ** -deriv
+    out = []
+    for i in range(n):
+        if mode == "interp" and i < hw:
+            y = fit_at(x[:window_length], ts, polyorder, deriv, i - hw)
+        elif mode == "interp" and i >= n - hw:
+            y = fit_at(x[n - window_length:], ts, polyorder, deriv, i - (n - 1 - hw))
+        else:
+            window = [pad(x, i + t, mode, Fraction(cval)) for t in ts]
+            y = fit_at(window, ts, polyorder, deriv, 0)
+        out.append(y * scale)
+    return [float(v) for v in out]
+
+
+def reference(x, window_length, polyorder, deriv, delta, mode, cval):
+    if scipy_savgol_filter is not None:
+        return list(
+            scipy_savgol_filter(
+                np.array(x, dtype=float), window_length, polyorder, deriv, delta, mode=mode, cval=cval
+            )
+        )
+    return exact_savgol_filter(x, window_length, polyorder, deriv, delta, mode, cval)
+
+
+DOCS = [2, 2, 5, 2, 1, 0, 1, 4, 9]
+NOISY = [((i * 37) % 23) - 11 + i / 4 for i in range(24)]
+CASES = [
+    (DOCS, 5, 2, 0, 1.0, "interp", 0.0),
+    (DOCS, 5, 2, 0, 1.0, "nearest", 0.0),
+    (DOCS, 5, 2, 1, 1.0, "interp", 0.0),
+    (NOISY, 7, 3, 0, 1.0, "interp", 0.0),
+    (NOISY, 7, 3, 1, 0.5, "interp", 0.0),
+    (NOISY, 9, 4, 2, 0.25, "interp", 0.0),
+    (NOISY, 7, 2, 0, 1.0, "mirror", 0.0),
+    (NOISY, 7, 2, 1, 2.0, "nearest", 0.0),
+    (NOISY, 5, 2, 0, 1.0, "constant", 0.0),
+    (NOISY, 5, 3, 0, 1.0, "constant", 2.5),
+    (NOISY, 11, 5, 0, 1.0, "wrap", 0.0),
+    (NOISY, 5, 1, 3, 1.0, "mirror", 0.0),
+    (NOISY, 3, 0, 0, 1.0, "interp", 0.0),
+]
+
+if __name__ == "__main__":
+    if scipy_savgol_filter is not None:
+        source = f"scipy {scipy.__version__}"
+    else:
+        source = "exact rational least squares, not scipy"
+    print(f"# savgol_filter reference values ({source}), from fixtures/gen_scipy_fixtures.py")
+    print("# window_length polyorder deriv delta mode cval | x | expected")
+    for x, wl, po, d, delta, mode, cval in CASES:
+        y = reference(x, wl, po, d, delta, mode, cval)
+        print(
+            f"{wl} {po} {d} {delta!r} {mode} {cval!r} | "
+            + " ".join(repr(float(v)) for v in x)
+            + " | "
+            + " ".join(repr(float(v)) for v in y)
+        )
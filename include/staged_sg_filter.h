@@ -0,0 +1,60 @@
+/*
+ * C interface to staged-sg-filter, see src/ffi.rs.
+ *
+ * Link against the cdylib or staticlib built by `cargo build --release`
+ * (libstaged_sg_filter.so / .dylib / .a, or staged_sg_filter.dll / .lib).
+ *
+ * Every function returns SGF_OK or one of the SGF_ERR_* codes and never
+ * unwinds into the caller. `in` and `out` must hold `n` elements each and
+ * either not overlap or be the same pointer, to filter in place.
+ */
+
+#ifndef STAGED_SG_FILTER_H
+#define STAGED_SG_FILTER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Status codes */
+#define SGF_OK 0
+#define SGF_ERR_NULL (-1)      /* `in` or `out` is NULL */
+#define SGF_ERR_WINDOW (-2)    /* `half_width` is 0 */
+#define SGF_ERR_ORDER (-3)     /* `order` is larger than 2 * half_width */
+#define SGF_ERR_LENGTH (-4)    /* `n` is smaller than 2 * half_width + 1 */
+#define SGF_ERR_MODE (-5)      /* `mode` is not an SGF_MODE_* constant */
+#define SGF_ERR_INTERNAL (-99) /* a bug in the library, please report it */
+
+/* Boundary modes, named after scipy.signal.savgol_filter */
+#define SGF_MODE_INTERP 0
+#define SGF_MODE_MIRROR 1
+#define SGF_MODE_NEAREST 2
+#define SGF_MODE_CONSTANT 3 /* pads with 0.0 */
+#define SGF_MODE_WRAP 4
+
+/* Smooths `in` into `out` with an order `order` fit over 2 * half_width + 1 samples. */
+int sgf_filter_f64(const double *in, double *out, size_t n, uint32_t half_width,
+                   uint32_t order, int mode);
+int sgf_filter_f32(const float *in, float *out, size_t n, uint32_t half_width,
+                   uint32_t order, int mode);
+
+/* Same as sgf_filter_*, writing the `deriv`-th derivative per sample instead.
+ * A `deriv` above `order` writes zeros. */
+int sgf_filter_deriv_f64(const double *in, double *out, size_t n,
+                         uint32_t half_width, uint32_t order, uint32_t deriv,
+                         int mode);
+int sgf_filter_deriv_f32(const float *in, float *out, size_t n,
+                         uint32_t half_width, uint32_t order, uint32_t deriv,
+                         int mode);
+
+/* Static, nul-terminated description of a status code. */
+const char *sgf_strerror(int code);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* STAGED_SG_FILTER_H */
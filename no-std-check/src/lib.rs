@@ -0,0 +1,45 @@
+//! Builds staged-sg-filter without `std`, the way firmware links it. Check it on a target that
+//! only ships `core`:
+//!
+//! ```sh
+//! rustup target add thumbv7em-none-eabihf
+//! cargo build --target thumbv7em-none-eabihf
+//! cargo build --target thumbv7em-none-eabihf --features libm
+//! ```
+//!
+//! A plain `cargo build` checks the same on the host, since the library itself is `#![no_std]`
+//! then and any use of `std` fails to compile.
+
+#![no_std]
+
+use staged_sg_filter::coeffs::get_coeffs;
+use staged_sg_filter::fixed::{sav_gol_q15, sav_gol_q31_u16};
+use staged_sg_filter::{sav_gol, sav_gol_dyn, sav_gol_dyn_f32, sav_gol_f32, sav_gol_with};
+
+pub fn smooth(buf: &mut [f64; 64], data: &[f64; 64]) {
+    sav_gol::<3, 2>(buf, data);
+}
+
+pub fn smooth_f32(buf: &mut [f32; 64], data: &[f32; 64]) {
+    sav_gol_f32::<12, 4>(buf, data);
+}
+
+pub fn smooth_with(buf: &mut [f64], data: &[f64]) {
+    sav_gol_with::<2>(buf, data, get_coeffs::<2, 3>());
+}
+
+pub fn smooth_dyn(buf: &mut [f64], data: &[f64], coeffs: &[f64]) {
+    sav_gol_dyn(buf, data, coeffs);
+}
+
+pub fn smooth_dyn_f32(buf: &mut [f32], data: &[f32], coeffs: &[f32]) {
+    sav_gol_dyn_f32(buf, data, coeffs);
+}
+
+pub fn smooth_q15(buf: &mut [i16], data: &[i16]) {
+    sav_gol_q15::<4, 2>(buf, data);
+}
+
+pub fn smooth_q31_u16(buf: &mut [u16], data: &[u16]) {
+    sav_gol_q31_u16::<4, 2>(buf, data);
+}
@@ -0,0 +1,199 @@
+//! Frequency domain characterization of Savitzky-Golay coefficient rows.
+//!
+//! Frequencies are normalized to cycles per sample, so they run from `0.0` up to the Nyquist
+//! frequency `0.5`. Every function takes a coefficient row with `2 * WINDOW + 1` taps centred on
+//! the middle one, like the rows returned by `get_coeffs`.
+
+use crate::coeffs::fit_coeffs;
+use std::f64::consts::PI;
+
+/// Magnitude and phase of a coefficient row sampled on a uniform frequency grid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrequencyResponse {
+    /// Frequencies in cycles per sample, from `0.0` to `0.5` inclusive
+    pub freq: Vec<f64>,
+    pub magnitude: Vec<f64>,
+    /// Phase in radians
+    pub phase: Vec<f64>,
+}
+
+/// Complex response `H(f) = sum_k c_k e^{-2 pi i f (k - WINDOW)}` of a row as `(re, im)`
+fn response(coeffs: &[f64], f: f64) -> (f64, f64) {
+    let centre = (coeffs.len() / 2) as f64;
+    coeffs
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(re, im), (k, c)| {
+            let w = -2.0 * PI * f * (k as f64 - centre);
+            (re + c * w.cos(), im + c * w.sin())
+        })
+}
+
+fn magnitude(coeffs: &[f64], f: f64) -> f64 {
+    let (re, im) = response(coeffs, f);
+    re.hypot(im)
+}
+
+/// Frequency response of `coeffs` on `n_points` evenly spaced frequencies in `0.0..=0.5`.
+/// ```
+///     use staged_sg_filter::analysis::frequency_response;
+///     use staged_sg_filter::coeffs::get_coeffs;
+///     let res = frequency_response(get_coeffs::<2, 2>(), 65);
+///     assert!((res.magnitude[0] - 1.0).abs() < 1e-12);
+///     assert_eq!(res.freq[64], 0.5);
+///```
+pub fn frequency_response(coeffs: &[f64], n_points: usize) -> FrequencyResponse {
+    if n_points < 2 {
+        panic!("n_points must be 2 <= n_points");
+    }
+    let freq: Vec<f64> = (0..n_points)
+        .map(|i| 0.5 * i as f64 / (n_points - 1) as f64)
+        .collect();
+    let (magnitude, phase) = freq
+        .iter()
+        .map(|&f| {
+            let (re, im) = response(coeffs, f);
+            (re.hypot(im), im.atan2(re))
+        })
+        .unzip();
+    FrequencyResponse {
+        freq,
+        magnitude,
+        phase,
+    }
+}
+
+/// First frequency at which the magnitude drops `3 dB` (a factor `1 / sqrt(2)`) below its DC gain.
+///
+/// Returns `None` for rows that never drop that far below Nyquist, and for rows with no DC gain
+/// such as derivatives.
+pub fn cutoff(coeffs: &[f64]) -> Option<f64> {
+    const STEPS: usize = 2048;
+    let dc = magnitude(coeffs, 0.0);
+    if dc < 1e-12 {
+        return None;
+    }
+    let level = dc / 2.0f64.sqrt();
+    let step = (1..=STEPS).find(|&i| magnitude(coeffs, 0.5 * i as f64 / STEPS as f64) < level)?;
+    // Bisect the crossing inside the grid step
+    let (mut lo, mut hi) = (
+        0.5 * (step - 1) as f64 / STEPS as f64,
+        0.5 * step as f64 / STEPS as f64,
+    );
+    for _ in 0..50 {
+        let mid = 0.5 * (lo + hi);
+        if magnitude(coeffs, mid) < level {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    Some(0.5 * (lo + hi))
+}
+
+/// Gain of the filter on white noise variance, the sum of squared taps.
+pub fn noise_gain(coeffs: &[f64]) -> f64 {
+    coeffs.iter().map(|c| c * c).sum()
+}
+
+/// Equivalent noise bandwidth in cycles per sample.
+///
+/// The width of the ideal low-pass filter with the same DC gain that lets through as much white
+/// noise as `coeffs`. Infinite for rows with no DC gain such as derivatives, whose sum only
+/// cancels to zero up to rounding.
+pub fn equivalent_bandwidth(coeffs: &[f64]) -> f64 {
+    let dc = coeffs.iter().sum::<f64>();
+    if dc.abs() < 1e-12 {
+        return f64::INFINITY;
+    }
+    noise_gain(coeffs) / (2.0 * dc * dc)
+}
+
+/// Suggests the `(WINDOW, M)` pair of `COEFFS` that best suppresses noise while keeping its
+/// cutoff at or above `target` cycles per sample.
+///
+/// Among the supported pairs that pass `target`, this is the one with the smallest
+/// `noise_gain`. Returns `None` when `target` lies above the Nyquist frequency.
+///
+/// The pairs are scored on the exact least-squares rows from `fit_coeffs`, since the rows of
+/// `COEFFS` drift from them as the order grows (by up to `1e-3` at `M = 10`).
+/// ```
+///     use staged_sg_filter::analysis::{cutoff, suggest};
+///     use staged_sg_filter::coeffs::fit_coeffs;
+///     let (window, m) = suggest(0.1).unwrap();
+///     assert!(cutoff(&fit_coeffs(window, m, 0, 0.0)).unwrap() >= 0.1);
+///```
+pub fn suggest(target: f64) -> Option<(usize, usize)> {
+    if !(0.0..=0.5).contains(&target) {
+        return None;
+    }
+    (1..=25)
+        .flat_map(|window| (1..=10).map(move |m| (window, m)))
+        // Orders past `2 * WINDOW` are not a least-squares fit anymore
+        .filter(|&(window, m)| m <= 2 * window)
+        .map(|(window, m)| ((window, m), fit_coeffs(window, m, 0, 0.0)))
+        .filter(|(_, coeffs)| cutoff(coeffs).unwrap_or(0.5) >= target)
+        .min_by(|(_, a), (_, b)| noise_gain(a).total_cmp(&noise_gain(b)))
+        .map(|(pair, _)| pair)
+}
+
+#[test]
+fn test_frequency_response() {
+    // Moving average of 3: H(f) = (1 + 2 cos(2 pi f)) / 3
+    let coeffs = [1.0 / 3.0; 3];
+    let res = frequency_response(&coeffs, 11);
+    res.freq
+        .iter()
+        .zip(res.magnitude.iter())
+        .for_each(|(f, m)| {
+            assert!((m - ((1.0 + 2.0 * (2.0 * PI * f).cos()) / 3.0).abs()).abs() < 1e-12)
+        });
+    // Symmetric rows have zero or `pi` phase
+    assert!(res
+        .phase
+        .iter()
+        .all(|p| p.abs() < 1e-12 || (p.abs() - PI).abs() < 1e-12));
+}
+
+#[test]
+fn test_cutoff() {
+    let coeffs = [1.0 / 3.0; 3];
+    let ans = ((3.0 / 2.0f64.sqrt() - 1.0) / 2.0).acos() / (2.0 * PI);
+    assert!((cutoff(&coeffs).unwrap() - ans).abs() < 1e-12);
+    // An identity row never rolls off, a derivative row has no DC gain
+    assert_eq!(cutoff(&[0.0, 1.0, 0.0]), None);
+    assert_eq!(cutoff(&[-0.5, 0.0, 0.5]), None);
+    // Higher orders keep more of the band
+    let rows = crate::coeffs::COEFFS;
+    assert!(cutoff(rows[3][9]).unwrap() > cutoff(rows[1][9]).unwrap());
+}
+
+#[test]
+fn test_noise_gain_bandwidth() {
+    let coeffs = [0.2; 5];
+    assert!((noise_gain(&coeffs) - 0.2).abs() < 1e-12);
+    assert!((equivalent_bandwidth(&coeffs) - 0.1).abs() < 1e-12);
+    assert!(equivalent_bandwidth(&[-0.5, 0.0, 0.5]).is_infinite());
+    for (half_width, order) in [(5, 3), (12, 4), (40, 6)] {
+        let row = crate::coeffs::fit_coeffs(half_width, order, 1, 0.0);
+        assert_eq!(equivalent_bandwidth(&row), f64::INFINITY);
+    }
+}
+
+#[test]
+fn test_suggest() {
+    for target in [0.02, 0.05, 0.1, 0.2, 0.3] {
+        let (window, m) = suggest(target).unwrap();
+        let coeffs = fit_coeffs(window, m, 0, 0.0);
+        let best = noise_gain(&coeffs);
+        assert!(cutoff(&coeffs).unwrap_or(0.5) >= target);
+        // No other pair that passes `target` smooths more
+        (1..=25)
+            .flat_map(|window| (1..=10).map(move |m| (window, m)))
+            .filter(|&(window, m)| m <= 2 * window)
+            .map(|(window, m)| fit_coeffs(window, m, 0, 0.0))
+            .filter(|coeffs| cutoff(coeffs).unwrap_or(0.5) >= target)
+            .for_each(|coeffs| assert!(noise_gain(&coeffs) >= best));
+    }
+    assert_eq!(suggest(0.6), None);
+}
@@ -0,0 +1,224 @@
+//! Minimal delimited text tables for `--format csv` and `--format tsv`.
+//!
+//! Fields may be quoted with `"`, with `""` standing for a literal quote. Records span a single
+//! line. Fields are kept as text so untouched columns are written back as they were read.
+
+use std::io::{self, Write};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub header: Option<Vec<String>>,
+    pub rows: Vec<Vec<String>>,
+    /// Line of the input each row was read from, for error messages
+    lines: Vec<usize>,
+}
+
+/// Splits a single record on `delim`, unquoting quoted fields
+fn split_record(line: &str, delim: char) -> Result<Vec<String>, String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            c if c == delim && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field".to_string());
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+/// Quotes `field` if it wouldn't survive a round trip otherwise
+fn quote(field: &str, delim: char) -> String {
+    if field.contains([delim, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+impl Table {
+    /// Parses `text`, taking the first record as the header if `has_header`. Blank lines are skipped.
+    pub fn parse(text: &str, delim: char, has_header: bool) -> Result<Table, String> {
+        let mut records = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                split_record(line, delim)
+                    .map(|fields| (i + 1, fields))
+                    .map_err(|e| format!("line {}: {e}", i + 1))
+            });
+        let header = match has_header {
+            true => records.next().transpose()?.map(|(_, fields)| fields),
+            false => None,
+        };
+        let (lines, rows): (Vec<usize>, Vec<Vec<String>>) = records
+            .collect::<Result<Vec<_>, String>>()?
+            .into_iter()
+            .unzip();
+        let width = header
+            .as_ref()
+            .or(rows.first())
+            .map_or(0, |fields| fields.len());
+        if let Some((line, row)) = lines
+            .iter()
+            .zip(rows.iter())
+            .find(|(_, r)| r.len() != width)
+        {
+            return Err(format!(
+                "line {line}: expected {width} fields, found {}",
+                row.len()
+            ));
+        }
+        Ok(Table {
+            header,
+            rows,
+            lines,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.header
+            .as_ref()
+            .or(self.rows.first())
+            .map_or(0, |fields| fields.len())
+    }
+
+    /// Resolves a column by header name, or else by its 1-based index
+    pub fn column_index(&self, spec: &str) -> Result<usize, String> {
+        if let Some(i) = self
+            .header
+            .as_ref()
+            .and_then(|header| header.iter().position(|name| name == spec))
+        {
+            return Ok(i);
+        }
+        match spec.parse::<usize>() {
+            Ok(i) if (1..=self.width()).contains(&i) => Ok(i - 1),
+            Ok(_) => Err(format!(
+                "column {spec} is out of range, the table has {} columns",
+                self.width()
+            )),
+            Err(_) => Err(format!("no column named `{spec}`")),
+        }
+    }
+
+    /// Parses column `col`, reporting the line and column of malformed fields
+    pub fn column<T: FromStr>(&self, col: usize) -> Result<Vec<T>, String> {
+        self.rows
+            .iter()
+            .zip(self.lines.iter())
+            .map(|(row, line)| {
+                let field = row[col].trim();
+                field.parse().map_err(|_| {
+                    format!("line {line}, column {}: invalid number `{field}`", col + 1)
+                })
+            })
+            .collect()
+    }
+
+    /// Replaces column `col` with `values`
+    pub fn replace(&mut self, col: usize, values: Vec<String>) {
+        self.rows
+            .iter_mut()
+            .zip(values)
+            .for_each(|(row, value)| row[col] = value);
+    }
+
+    /// Appends a column named `name` holding `values`
+    pub fn append(&mut self, name: &str, values: Vec<String>) {
+        if let Some(header) = self.header.as_mut() {
+            header.push(name.to_string());
+        }
+        self.rows
+            .iter_mut()
+            .zip(values)
+            .for_each(|(row, value)| row.push(value));
+    }
+
+    pub fn name(&self, col: usize) -> String {
+        match &self.header {
+            Some(header) => header[col].clone(),
+            None => format!("{}", col + 1),
+        }
+    }
+
+    pub fn write(&self, mut out: impl Write, delim: char) -> io::Result<()> {
+        self.header
+            .iter()
+            .chain(self.rows.iter())
+            .try_for_each(|fields| {
+                let record: Vec<String> = fields.iter().map(|f| quote(f, delim)).collect();
+                writeln!(out, "{}", record.join(&delim.to_string()))
+            })?;
+        out.flush()
+    }
+}
+
+#[test]
+fn test_split_record() {
+    assert_eq!(
+        split_record("a,\"b,c\",\"say \"\"hi\"\"\",", ','),
+        Ok(vec![
+            "a".to_string(),
+            "b,c".to_string(),
+            "say \"hi\"".to_string(),
+            "".to_string()
+        ])
+    );
+    assert_eq!(
+        split_record("1\t2", '\t'),
+        Ok(vec!["1".to_string(), "2".to_string()])
+    );
+    assert!(split_record("\"open", ',').is_err());
+}
+
+#[test]
+fn test_table_round_trip() {
+    let text = "time,\"x, raw\",y\n0,1.5,2\n\n1,2.5,3\n";
+    let table = Table::parse(text, ',', true).unwrap();
+    assert_eq!(table.rows.len(), 2);
+    let mut out = vec![];
+    table.write(&mut out, ',').unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), text.replace("\n\n", "\n"));
+}
+
+#[test]
+fn test_table_columns() {
+    let table = Table::parse("t,x\n0,1\n1,oops\n", ',', true).unwrap();
+    assert_eq!(table.column_index("x"), Ok(1));
+    assert_eq!(table.column_index("1"), Ok(0));
+    assert!(table.column_index("3").is_err());
+    assert!(table.column_index("y").is_err());
+    assert_eq!(table.column::<f64>(0), Ok(vec![0.0, 1.0]));
+    assert_eq!(
+        table.column::<f64>(1),
+        Err("line 3, column 2: invalid number `oops`".to_string())
+    );
+    assert_eq!(
+        Table::parse("a,b\n1\n", ',', true),
+        Err("line 2: expected 2 fields, found 1".to_string())
+    );
+}
+
+#[test]
+fn test_table_append_replace() {
+    let mut table = Table::parse("1\t2\n3\t4\n", '\t', false).unwrap();
+    table.replace(0, vec!["5".to_string(), "6".to_string()]);
+    table.append("2_smoothed", vec!["7".to_string(), "8".to_string()]);
+    let mut out = vec![];
+    table.write(&mut out, '\t').unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "5\t2\t7\n6\t4\t8\n");
+}
@@ -0,0 +1,833 @@
+//! `sgfilter`: Savitzky-Golay filtering from the command line.
+//!
+//! Reads whitespace separated samples from a file or stdin and writes the filtered signal, one
+//! sample per line, so the filters can be used from shell pipelines. Columns of CSV and TSV
+//! tables, the rows or columns of `.npy` arrays and the channels of WAV recordings can be
+//! filtered too, and raw binary signals are streamed in blocks so they can be larger than memory.
+
+mod csv;
+
+use staged_sg_filter::boundary::Mode;
+use staged_sg_filter::filter::SavGolFilter;
+use staged_sg_filter::npy::{read_npy, write_npy, Npy, NpyData};
+use staged_sg_filter::raw::{filter_raw, filter_raw_f32};
+use staged_sg_filter::wav::{read_wav, write_wav, Wav};
+
+use csv::Table;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: sgfilter [OPTIONS] [INPUT]
+
+Filters the whitespace separated samples of INPUT (stdin when missing or `-`) and writes the
+result, one sample per line.
+
+With `--format csv` or `--format tsv` (the default for `.csv` and `.tsv` inputs) the selected
+columns of the table are filtered instead, leaving every other column as it was.
+
+With `--format npy` (the default for `.npy` inputs) a 1D or 2D `<f4`/`<f8` array is filtered
+along `--axis` and written back as `.npy` with the same dtype, ignoring `--precision`.
+
+With `--format raw` (the default for `.raw` and `.bin` inputs) INPUT holds little-endian samples
+of `--precision`, which are streamed through the filter `--block` samples at a time and written
+in the same format. Memory use doesn't grow with the size of the signal, but `--mode wrap` is not
+available.
+
+With `--format wav` (the default for `.wav` inputs) each channel of a 16/24/32-bit PCM or float
+WAV file is filtered in f32 and written back in the original sample format, with triangular
+dither when quantizing to integers.
+
+Options:
+  -w, --window <N>      half-width of the window, which holds 2 * N + 1 samples [default: 2]
+  -m, --order <M>       order of the fitted polynomial, at most 2 * N [default: 2]
+  -d, --deriv <D>       derivative to compute, per sample [default: 0]
+      --mode <MODE>     boundary handling: interp, mirror, nearest, constant or wrap [default: interp]
+      --cval <C>        padding value for `--mode constant` [default: 0]
+  -p, --precision <P>   f32 or f64 [default: f64]
+  -o, --output <FILE>   write to FILE instead of stdout
+  -f, --format <F>      lines, csv, tsv, npy, raw or wav [default: from the INPUT extension, else lines]
+  -c, --columns <COLS>  comma separated column names or 1-based indices to filter
+      --append          append `<column>_smoothed` columns instead of replacing the originals
+      --no-header       the table has no header row
+      --axis <A>        npy axis to filter along, negative counts from the end [default: -1]
+      --block <N>       samples read at a time with `--format raw` [default: 65536]
+      --mmap            map the `--format raw` INPUT into memory instead of reading it
+      --no-dither       round WAV samples to integers without dither
+  -h, --help            print this message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Precision {
+    F32,
+    F64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Lines,
+    Csv,
+    Tsv,
+    Npy,
+    Raw,
+    Wav,
+}
+
+impl Format {
+    fn delimiter(self) -> char {
+        match self {
+            Format::Tsv => '\t',
+            _ => ',',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Args {
+    window: usize,
+    order: usize,
+    deriv: usize,
+    mode: Mode,
+    precision: Precision,
+    input: Option<String>,
+    output: Option<String>,
+    format: Option<Format>,
+    columns: Vec<String>,
+    append: bool,
+    header: bool,
+    axis: isize,
+    block: usize,
+    mmap: bool,
+    dither: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            window: 2,
+            order: 2,
+            deriv: 0,
+            mode: Mode::Interp,
+            precision: Precision::F64,
+            input: None,
+            output: None,
+            format: None,
+            columns: vec![],
+            append: false,
+            header: true,
+            axis: -1,
+            block: 1 << 16,
+            mmap: false,
+            dither: true,
+        }
+    }
+}
+
+/// Parses the command line, `Ok(None)` means `--help` was asked for
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut res = Args::default();
+    let mut cval = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // Accept both `--flag value` and `--flag=value`
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("missing value for `{flag}`"))
+        };
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-w" | "--window" => res.window = parse_value(&flag, &value()?)?,
+            "-m" | "--order" => res.order = parse_value(&flag, &value()?)?,
+            "-d" | "--deriv" => res.deriv = parse_value(&flag, &value()?)?,
+            "--mode" => res.mode = value()?.parse()?,
+            "--cval" => cval = Some(parse_value::<f64>(&flag, &value()?)?),
+            "-p" | "--precision" => {
+                res.precision = match value()?.as_str() {
+                    "f32" => Precision::F32,
+                    "f64" => Precision::F64,
+                    other => {
+                        return Err(format!("unknown precision `{other}`, expected f32 or f64"))
+                    }
+                }
+            }
+            "-o" | "--output" => res.output = Some(value()?),
+            "-f" | "--format" => {
+                res.format = match value()?.as_str() {
+                    "lines" => Some(Format::Lines),
+                    "csv" => Some(Format::Csv),
+                    "tsv" => Some(Format::Tsv),
+                    "npy" => Some(Format::Npy),
+                    "raw" => Some(Format::Raw),
+                    "wav" => Some(Format::Wav),
+                    other => {
+                        return Err(format!(
+                            "unknown format `{other}`, expected lines, csv, tsv, npy, raw or wav"
+                        ))
+                    }
+                }
+            }
+            "-c" | "--columns" => res.columns = value()?.split(',').map(String::from).collect(),
+            "--append" => res.append = true,
+            "--no-header" => res.header = false,
+            "--axis" => res.axis = parse_value(&flag, &value()?)?,
+            "--block" => res.block = parse_value(&flag, &value()?)?,
+            "--mmap" => res.mmap = true,
+            "--no-dither" => res.dither = false,
+            "-" => res.input = None,
+            _ if flag.starts_with('-') => return Err(format!("unknown option `{flag}`")),
+            _ if res.input.is_some() => return Err(format!("unexpected argument `{arg}`")),
+            _ => res.input = Some(arg),
+        }
+    }
+    match (cval, res.mode) {
+        (Some(c), Mode::Constant(_)) => res.mode = Mode::Constant(c),
+        (Some(_), _) => return Err("`--cval` only applies to `--mode constant`".to_string()),
+        _ => {}
+    }
+    if res.window == 0 {
+        return Err("`--window` must be at least 1".to_string());
+    }
+    if res.order > 2 * res.window {
+        return Err(format!(
+            "`--order` must be at most 2 * window = {}",
+            2 * res.window
+        ));
+    }
+    if res.format.is_none() {
+        res.format = match res.input.as_deref() {
+            Some(path) if path.ends_with(".csv") => Some(Format::Csv),
+            Some(path) if path.ends_with(".tsv") => Some(Format::Tsv),
+            Some(path) if path.ends_with(".npy") => Some(Format::Npy),
+            Some(path) if path.ends_with(".raw") || path.ends_with(".bin") => Some(Format::Raw),
+            Some(path) if path.ends_with(".wav") => Some(Format::Wav),
+            _ => Some(Format::Lines),
+        };
+    }
+    let table = matches!(res.format, Some(Format::Csv | Format::Tsv));
+    if !table && (!res.columns.is_empty() || res.append) {
+        return Err("`--columns` and `--append` need `--format csv` or `--format tsv`".to_string());
+    }
+    if table && res.columns.is_empty() {
+        return Err("`--columns` is required for tables".to_string());
+    }
+    if res.format == Some(Format::Raw) {
+        if res.block == 0 {
+            return Err("`--block` must be at least 1".to_string());
+        }
+        if res.mode == Mode::Wrap {
+            return Err("`--mode wrap` can't be streamed with `--format raw`".to_string());
+        }
+        // Raw input is streamed to the output, `run` also catches other paths to the same file
+        if res.input.is_some() && res.input == res.output {
+            return Err("`--format raw` can't write to its own input".to_string());
+        }
+    }
+    if res.mmap {
+        if !cfg!(feature = "mmap") {
+            return Err("`--mmap` needs sgfilter built with the `mmap` feature".to_string());
+        }
+        if res.format != Some(Format::Raw) || res.input.is_none() {
+            return Err("`--mmap` needs `--format raw` and an INPUT file".to_string());
+        }
+    }
+    Ok(Some(res))
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value `{value}` for `{flag}`"))
+}
+
+/// Parses whitespace separated samples, reporting the line of malformed ones
+fn parse_samples<T: std::str::FromStr>(text: &str) -> Result<Vec<T>, String> {
+    text.lines()
+        .enumerate()
+        .flat_map(|(line, s)| s.split_whitespace().map(move |x| (line + 1, x)))
+        .map(|(line, x)| {
+            x.parse()
+                .map_err(|_| format!("invalid sample `{x}` on line {line}"))
+        })
+        .collect()
+}
+
+/// Builds the filter once, to be shared by every signal of the input
+fn build_filter(args: &Args) -> Result<SavGolFilter, String> {
+    SavGolFilter::new(args.window, args.order, args.deriv, args.mode)
+}
+
+fn check_len(filter: &SavGolFilter, len: usize) -> Result<(), String> {
+    let window = 2 * filter.half_width() + 1;
+    if len < window {
+        return Err(format!(
+            "the signal holds {len} samples, fewer than the window's {window}"
+        ));
+    }
+    Ok(())
+}
+
+fn filter_f64(filter: &SavGolFilter, data: &[f64]) -> Result<Vec<f64>, String> {
+    check_len(filter, data.len())?;
+    let mut buf = vec![0.0; data.len()];
+    filter.apply(&mut buf, data);
+    Ok(buf)
+}
+
+fn filter_f32(filter: &SavGolFilter, data: &[f32]) -> Result<Vec<f32>, String> {
+    check_len(filter, data.len())?;
+    let mut buf = vec![0.0; data.len()];
+    filter.apply_f32(&mut buf, data);
+    Ok(buf)
+}
+
+fn write_samples<T: std::fmt::Display>(out: impl Write, samples: &[T]) -> io::Result<()> {
+    let mut out = BufWriter::new(out);
+    samples.iter().try_for_each(|x| writeln!(out, "{x}"))?;
+    out.flush()
+}
+
+/// Whether `output` is an existing path to the same file as `input`, through `./`, `..`,
+/// symbolic or hard links
+fn same_file(input: &str, output: &str) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (std::fs::metadata(input), std::fs::metadata(output)) {
+            (Ok(i), Ok(o)) => (i.dev(), i.ino()) == (o.dev(), o.ino()),
+            _ => false,
+        }
+    }
+    #[cfg(not(unix))]
+    match (std::fs::canonicalize(input), std::fs::canonicalize(output)) {
+        (Ok(i), Ok(o)) => i == o,
+        _ => false,
+    }
+}
+
+/// Opens the output, once the result is complete so that an error leaves an existing file as it
+/// was
+fn create_output(args: &Args) -> Result<Box<dyn Write>, String> {
+    Ok(match &args.output {
+        Some(path) => {
+            Box::new(File::create(path).map_err(|e| format!("cannot create `{path}`: {e}"))?)
+        }
+        None => Box::new(io::stdout().lock()),
+    })
+}
+
+fn open_input(args: &Args) -> Result<Box<dyn Read>, String> {
+    Ok(match &args.input {
+        Some(path) => Box::new(File::open(path).map_err(|e| format!("cannot open `{path}`: {e}"))?),
+        None => Box::new(io::stdin().lock()),
+    })
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    if args.format == Some(Format::Raw) {
+        // Raw input is streamed while the output is written, so the output must not be the input,
+        // least of all while it is mapped
+        if let (Some(input), Some(output)) = (&args.input, &args.output) {
+            if same_file(input, output) {
+                return Err("`--format raw` can't write to its own input".to_string());
+            }
+        }
+        #[cfg(feature = "mmap")]
+        if let (true, Some(path)) = (args.mmap, &args.input) {
+            // Checked above, the output is not the mapped file
+            let input = unsafe { staged_sg_filter::raw::map_raw(path) }
+                .map_err(|e| format!("cannot map `{path}`: {e}"))?;
+            return write_raw(args, &input[..]);
+        }
+        return write_raw(args, io::BufReader::new(open_input(args)?));
+    }
+
+    // Everything else is filtered into memory before the output is opened
+    let mut input = open_input(args)?;
+    let mut out = vec![];
+    match args.format {
+        Some(Format::Wav) => {
+            let wav = run_wav(args, io::BufReader::new(input))?;
+            write_wav(&mut out, &wav, args.dither)
+        }
+        Some(Format::Npy) => {
+            let npy = run_npy(args, io::BufReader::new(input))?;
+            write_npy(&mut out, &npy)
+        }
+        _ => {
+            let mut text = String::new();
+            input
+                .read_to_string(&mut text)
+                .map_err(|e| format!("cannot read input: {e}"))?;
+            let filter = build_filter(args)?;
+            match (args.format, args.precision) {
+                (Some(Format::Csv | Format::Tsv), _) => Ok(run_table(args, &text, &mut out)?),
+                (_, Precision::F64) => {
+                    write_samples(&mut out, &filter_f64(&filter, &parse_samples(&text)?)?)
+                }
+                (_, Precision::F32) => {
+                    write_samples(&mut out, &filter_f32(&filter, &parse_samples(&text)?)?)
+                }
+            }
+        }
+    }
+    .map_err(|e| format!("cannot write output: {e}"))?;
+    let mut output = create_output(args)?;
+    output
+        .write_all(&out)
+        .and_then(|_| output.flush())
+        .map_err(|e| format!("cannot write output: {e}"))
+}
+
+/// Streams raw little-endian samples of `args.precision` from `input` to `out`
+fn run_raw(args: &Args, input: impl Read, out: impl Write) -> Result<(), String> {
+    let (w, m, d) = (args.window, args.order, args.deriv);
+    match args.precision {
+        Precision::F64 => filter_raw(input, out, w, m, d, args.mode, args.block),
+        Precision::F32 => filter_raw_f32(input, out, w, m, d, args.mode, args.block),
+    }
+    .map(|_| ())
+    .map_err(|e| format!("cannot filter raw input: {e}"))
+}
+
+/// `run_raw` to the output. A file is written as a temporary file next to it, which only replaces
+/// it once the whole input has been filtered
+fn write_raw(args: &Args, input: impl Read) -> Result<(), String> {
+    let Some(output) = &args.output else {
+        return run_raw(args, input, BufWriter::new(io::stdout().lock()));
+    };
+    let path = std::path::Path::new(output);
+    let name = path
+        .file_name()
+        .map_or("sgfilter".into(), |n| n.to_string_lossy());
+    let tmp = path.with_file_name(format!(".{name}.{}.tmp", std::process::id()));
+    let res = File::create(&tmp)
+        .map_err(|e| format!("cannot create `{}`: {e}", tmp.display()))
+        .and_then(|file| run_raw(args, input, BufWriter::new(file)))
+        .and_then(|_| {
+            std::fs::rename(&tmp, path).map_err(|e| format!("cannot create `{output}`: {e}"))
+        });
+    if res.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    res
+}
+
+/// Reads a WAV file and filters each of its channels in f32
+fn run_wav(args: &Args, input: impl Read) -> Result<Wav, String> {
+    let mut wav = read_wav(input).map_err(|e| format!("cannot read wav input: {e}"))?;
+    let filter = build_filter(args)?;
+    filter_lanes(&mut wav.samples, wav.channels, false, |lane| {
+        filter_f32(&filter, lane)
+    })?;
+    Ok(wav)
+}
+
+/// Reads an npy array and filters it along `args.axis`, keeping its dtype
+fn run_npy(args: &Args, input: impl Read) -> Result<Npy, String> {
+    let mut npy = read_npy(input).map_err(|e| format!("cannot read npy input: {e}"))?;
+    let dims = npy.shape.len() as isize;
+    if !(-dims..dims).contains(&args.axis) {
+        return Err(format!(
+            "`--axis {}` is out of range for an array with {dims} dimensions",
+            args.axis
+        ));
+    }
+    let along_last = args.axis.rem_euclid(dims) == dims - 1;
+    let row_len = npy.shape[npy.shape.len() - 1];
+    let filter = build_filter(args)?;
+    match &mut npy.data {
+        NpyData::F64(v) => filter_lanes(v, row_len, along_last, |lane| filter_f64(&filter, lane))?,
+        NpyData::F32(v) => filter_lanes(v, row_len, along_last, |lane| filter_f32(&filter, lane))?,
+    }
+    Ok(npy)
+}
+
+/// Filters a C-ordered 2D array with rows of `row_len` elements, either along its rows
+/// (`along_last`) or along its columns
+fn filter_lanes<T: Copy>(
+    data: &mut [T],
+    row_len: usize,
+    along_last: bool,
+    filter: impl Fn(&[T]) -> Result<Vec<T>, String>,
+) -> Result<(), String> {
+    if data.is_empty() {
+        return Err("the array is empty".to_string());
+    }
+    if along_last {
+        return data.chunks_exact_mut(row_len).try_for_each(|row| {
+            row.copy_from_slice(&filter(row)?);
+            Ok(())
+        });
+    }
+    (0..row_len).try_for_each(|col| {
+        let lane: Vec<T> = data.iter().skip(col).step_by(row_len).copied().collect();
+        data.iter_mut()
+            .skip(col)
+            .step_by(row_len)
+            .zip(filter(&lane)?)
+            .for_each(|(x, y)| *x = y);
+        Ok(())
+    })
+}
+
+/// Filters the selected columns of a CSV or TSV table
+fn run_table(args: &Args, text: &str, out: impl Write) -> Result<(), String> {
+    let delim = args.format.map_or(',', Format::delimiter);
+    let mut table = Table::parse(text, delim, args.header)?;
+    let mut columns = args
+        .columns
+        .iter()
+        .map(|spec| table.column_index(spec))
+        .collect::<Result<Vec<usize>, String>>()?;
+    // The same column may be selected both by name and by index
+    let mut seen = vec![false; table.width()];
+    columns.retain(|&col| !std::mem::replace(&mut seen[col], true));
+    let filter = build_filter(args)?;
+    for col in columns {
+        let filtered: Vec<String> = match args.precision {
+            Precision::F64 => filter_f64(&filter, &table.column(col)?)?
+                .iter()
+                .map(f64::to_string)
+                .collect(),
+            Precision::F32 => filter_f32(&filter, &table.column(col)?)?
+                .iter()
+                .map(f32::to_string)
+                .collect(),
+        };
+        if args.append {
+            let name = format!("{}_smoothed", table.name(col));
+            table.append(&name, filtered);
+        } else {
+            table.replace(col, filtered);
+        }
+    }
+    table
+        .write(BufWriter::new(out), delim)
+        .map_err(|e| format!("cannot write output: {e}"))
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("sgfilter: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("sgfilter: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+fn args(line: &str) -> Result<Option<Args>, String> {
+    parse_args(line.split_whitespace().map(String::from))
+}
+
+#[test]
+fn test_parse_args() {
+    assert_eq!(
+        args(""),
+        Ok(Some(Args {
+            format: Some(Format::Lines),
+            ..Args::default()
+        }))
+    );
+    assert_eq!(args("--help"), Ok(None));
+    let res = args("-w 5 --order=3 -d 1 --mode constant --cval 2.5 -p f32 -o out.txt in.txt")
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        res,
+        Args {
+            window: 5,
+            order: 3,
+            deriv: 1,
+            mode: Mode::Constant(2.5),
+            precision: Precision::F32,
+            input: Some("in.txt".to_string()),
+            output: Some("out.txt".to_string()),
+            format: Some(Format::Lines),
+            ..Args::default()
+        }
+    );
+    let res = args("data.csv -c speed,3 --append").unwrap().unwrap();
+    assert_eq!(res.format, Some(Format::Csv));
+    assert_eq!(res.columns, vec!["speed".to_string(), "3".to_string()]);
+    assert!(res.append);
+    let res = args("-f tsv -c 1 --no-header").unwrap().unwrap();
+    assert_eq!(res.format, Some(Format::Tsv));
+    assert!(!res.header);
+}
+
+#[test]
+fn test_parse_args_errors() {
+    assert!(args("--window").unwrap_err().contains("missing value"));
+    assert!(args("--window x").unwrap_err().contains("invalid value"));
+    assert!(args("--window 0").is_err());
+    assert!(args("-w 1 -m 3").unwrap_err().contains("at most"));
+    assert!(args("--mode reflect").unwrap_err().contains("unknown mode"));
+    assert!(args("--cval 1").is_err());
+    assert!(args("-p f16").is_err());
+    assert!(args("--frobnicate").is_err());
+    assert!(args("a.txt b.txt").is_err());
+    assert!(args("data.csv").unwrap_err().contains("required"));
+    assert!(args("-c 1").unwrap_err().contains("need"));
+    assert!(args("-f json").is_err());
+    assert!(args("a.npy -c 1").is_err());
+    assert!(args("a.raw --mode wrap").unwrap_err().contains("wrap"));
+    assert!(args("a.bin --block 0").is_err());
+    assert!(args("a.raw -o a.raw").unwrap_err().contains("own input"));
+    let mmap = if cfg!(feature = "mmap") {
+        "INPUT"
+    } else {
+        "feature"
+    };
+    assert!(args("a.txt --mmap").unwrap_err().contains(mmap));
+    assert!(args("-f raw --mmap").unwrap_err().contains(mmap));
+}
+
+#[test]
+fn test_parse_samples() {
+    assert_eq!(
+        parse_samples::<f64>("1 2\n3.5\n\n-4e1"),
+        Ok(vec![1.0, 2.0, 3.5, -40.0])
+    );
+    assert_eq!(
+        parse_samples::<f64>("1\n2\nthree"),
+        Err("invalid sample `three` on line 3".to_string())
+    );
+}
+
+#[test]
+fn test_filter() {
+    let v: Vec<f64> = (0..20).map(|i| (i * i) as f64).collect();
+    let args = Args {
+        deriv: 1,
+        ..Args::default()
+    };
+    let res = filter_f64(&build_filter(&args).unwrap(), &v).unwrap();
+    res.iter()
+        .enumerate()
+        .for_each(|(i, r)| assert!((r - 2.0 * i as f64).abs() < 1e-9));
+
+    let v: Vec<f32> = v.iter().map(|&x| x as f32).collect();
+    let filter = build_filter(&Args::default()).unwrap();
+    let res = filter_f32(&filter, &v).unwrap();
+    res.iter()
+        .zip(v.iter())
+        .for_each(|(r, x)| assert!((r - x).abs() < 1e-3));
+    assert!(filter_f32(&filter, &v[..4]).is_err());
+}
+
+#[test]
+fn test_run_table() {
+    let text: String = std::iter::once("time,x,label\n".to_string())
+        .chain((0..8).map(|i| format!("{i},{},p{i}\n", i * i)))
+        .collect();
+    let args = parse_args(
+        ["-c", "x", "-d", "1", "-f", "csv", "--append"]
+            .iter()
+            .map(|s| s.to_string()),
+    )
+    .unwrap()
+    .unwrap();
+    let mut out = vec![];
+    run_table(&args, &text, &mut out).unwrap();
+    let res = Table::parse(std::str::from_utf8(&out).unwrap(), ',', true).unwrap();
+    assert_eq!(
+        res.header,
+        Some(vec![
+            "time".to_string(),
+            "x".to_string(),
+            "label".to_string(),
+            "x_smoothed".to_string()
+        ])
+    );
+    assert_eq!(
+        res.column::<f64>(1),
+        Table::parse(&text, ',', true).unwrap().column(1)
+    );
+    res.column::<f64>(3)
+        .unwrap()
+        .iter()
+        .enumerate()
+        .for_each(|(i, d)| assert!((d - 2.0 * i as f64).abs() < 1e-9));
+    assert_eq!(res.rows[5][2], "p5");
+
+    let bad = text.replace("16,p4", "1 6,p4");
+    assert_eq!(
+        run_table(&args, &bad, vec![]),
+        Err("line 6, column 2: invalid number `1 6`".to_string())
+    );
+}
+
+#[test]
+fn test_run_npy() {
+    // Rows grow as `i^2` down the columns and `10 j^2` along the rows
+    let (rows, cols) = (6, 7);
+    let v: Vec<f32> = (0..rows * cols)
+        .map(|k| ((k / cols) * (k / cols) + 10 * (k % cols) * (k % cols)) as f32)
+        .collect();
+    let npy = Npy {
+        shape: vec![rows, cols],
+        data: NpyData::F32(v),
+    };
+    let mut bytes = vec![];
+    write_npy(&mut bytes, &npy).unwrap();
+    for axis in [-2isize, -1, 0, 1] {
+        let args = Args {
+            deriv: 1,
+            axis,
+            format: Some(Format::Npy),
+            ..Args::default()
+        };
+        let res = run_npy(&args, bytes.as_slice()).unwrap();
+        assert_eq!(res.shape, vec![rows, cols]);
+        let NpyData::F32(res) = res.data else {
+            panic!("dtype changed");
+        };
+        res.iter().enumerate().for_each(|(k, d)| {
+            let slope = match axis.rem_euclid(2) {
+                0 => 2.0 * (k / cols) as f32,
+                _ => 20.0 * (k % cols) as f32,
+            };
+            assert!((d - slope).abs() < 1e-3);
+        });
+    }
+    let args = Args {
+        axis: 2,
+        ..Args::default()
+    };
+    assert!(run_npy(&args, bytes.as_slice())
+        .unwrap_err()
+        .contains("out of range"));
+}
+
+#[test]
+fn test_run_raw() {
+    let v: Vec<f32> = (0..50).map(|i| (i * i) as f32).collect();
+    let bytes: Vec<u8> = v.iter().flat_map(|x| x.to_le_bytes()).collect();
+    let args = parse_args(
+        ["in.raw", "-p", "f32", "-d", "1", "--block", "7"]
+            .iter()
+            .map(|s| s.to_string()),
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(args.format, Some(Format::Raw));
+    let mut out = vec![];
+    run_raw(&args, bytes.as_slice(), &mut out).unwrap();
+    let res: Vec<f32> = out
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+    assert_eq!(res, filter_f32(&build_filter(&args).unwrap(), &v).unwrap());
+    assert!(run_raw(&args, &bytes[..8], vec![])
+        .unwrap_err()
+        .contains("fewer samples"));
+}
+
+#[test]
+fn test_run_wav() {
+    use staged_sg_filter::wav::SampleFormat;
+    // A ramp on the left channel and a parabola on the right
+    let frames = 40;
+    let wav = Wav {
+        channels: 2,
+        sample_rate: 44100,
+        format: SampleFormat::Int24,
+        samples: (0..frames)
+            .flat_map(|i| [i as f32 / 64.0, (i * i) as f32 / 4096.0])
+            .collect(),
+    };
+    let mut bytes = vec![];
+    write_wav(&mut bytes, &wav, false).unwrap();
+    let args = parse_args(
+        ["in.wav", "-w", "3", "-d", "1"]
+            .iter()
+            .map(|s| s.to_string()),
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(args.format, Some(Format::Wav));
+    let res = run_wav(&args, bytes.as_slice()).unwrap();
+    assert_eq!(res.format, SampleFormat::Int24);
+    res.samples.chunks_exact(2).enumerate().for_each(|(i, lr)| {
+        assert!((lr[0] - 1.0 / 64.0).abs() < 1e-5);
+        assert!((lr[1] - 2.0 * i as f32 / 4096.0).abs() < 1e-5);
+    });
+}
+
+#[test]
+fn test_same_file() {
+    let dir = std::env::temp_dir().join(format!("sgfilter_same_file_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    std::fs::write(path("a.raw"), [0u8; 8]).unwrap();
+    std::fs::write(path("b.raw"), [0u8; 8]).unwrap();
+    std::fs::hard_link(path("a.raw"), path("hard.raw")).unwrap();
+    assert!(same_file(&path("a.raw"), &path("a.raw")));
+    assert!(same_file(
+        &path("a.raw"),
+        &format!("{}/./a.raw", dir.display())
+    ));
+    assert!(same_file(&path("a.raw"), &path("hard.raw")));
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(path("a.raw"), path("sym.raw")).unwrap();
+        assert!(same_file(&path("a.raw"), &path("sym.raw")));
+    }
+    assert!(!same_file(&path("a.raw"), &path("b.raw")));
+    assert!(!same_file(&path("a.raw"), &path("missing.raw")));
+
+    // The aliased input is left as it was
+    let args = args(&format!("{} -o {}", path("a.raw"), path("hard.raw")))
+        .unwrap()
+        .unwrap();
+    assert!(run(&args).unwrap_err().contains("own input"));
+    assert_eq!(std::fs::read(path("a.raw")).unwrap(), [0u8; 8]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_run_keeps_output_on_error() {
+    let dir = std::env::temp_dir().join(format!("sgfilter_keep_output_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    std::fs::write(path("bad.txt"), "1 2 3\n4 five 6\n").unwrap();
+    std::fs::write(path("short.raw"), [0u8; 3 * 8]).unwrap();
+    std::fs::write(path("good.raw"), [0u8; 10 * 8]).unwrap();
+    let run_line = |line: &str| run(&args(line).unwrap().unwrap());
+    for line in [
+        format!("{} -o {}", path("bad.txt"), path("out")),
+        format!("{} -w 2 -o {}", path("short.raw"), path("out")),
+        format!("{} -f npy -o {}", path("bad.txt"), path("out")),
+    ] {
+        std::fs::write(path("out"), "keep").unwrap();
+        assert!(run_line(&line).is_err(), "{line}");
+        assert_eq!(std::fs::read_to_string(path("out")).unwrap(), "keep");
+    }
+    run_line(&format!("{} -w 2 -o {}", path("good.raw"), path("out"))).unwrap();
+    assert_eq!(std::fs::read(path("out")).unwrap(), [0u8; 10 * 8]);
+    // No temporary file is left behind either way
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 4);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,274 @@
+//! Boundary handling for the fringes of a signal.
+//!
+//! The kernels only write the elements whose whole window fits inside `data`, which leaves
+//! `half_width` elements untouched on either end of `buf`. `fill_fringes` computes those
+//! according to a `Mode`, following the naming of `scipy.signal.savgol_filter`.
+
+use crate::coeffs::fit_coeffs;
+use crate::dot_prod_update;
+use std::str::FromStr;
+
+/// How the fringes of the signal are filtered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// Fit the polynomial to the first and last windows and evaluate it on the fringes
+    Interp,
+    /// Reflect about the edge elements: `4 3 2 | 1 2 3 4 | 3 2 1`
+    Mirror,
+    /// Repeat the edge elements: `1 1 1 | 1 2 3 4 | 4 4 4`
+    Nearest,
+    /// Pad with a constant value: `c c c | 1 2 3 4 | c c c`
+    Constant(f64),
+    /// Wrap around: `2 3 4 | 1 2 3 4 | 1 2 3`
+    Wrap,
+}
+
+/// Parses the scipy mode names, `constant` pads with `0.0`.
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "interp" => Ok(Mode::Interp),
+            "mirror" => Ok(Mode::Mirror),
+            "nearest" => Ok(Mode::Nearest),
+            "constant" => Ok(Mode::Constant(0.0)),
+            "wrap" => Ok(Mode::Wrap),
+            _ => Err(format!(
+                "unknown mode `{s}`, expected one of interp, mirror, nearest, constant, wrap"
+            )),
+        }
+    }
+}
+
+/// Fills the `half_width` elements on either end of `buf` that the kernels leave untouched.
+///
+/// The fringes are filtered with the `deriv`-th derivative of an order `order` fit, so that
+/// together with `sav_gol_dyn(buf, data, &fit_coeffs(half_width, order, deriv, 0.0))` the whole
+/// of `buf` is written.
+/// ```
+///     use staged_sg_filter::boundary::{fill_fringes, Mode};
+///     use staged_sg_filter::coeffs::fit_coeffs;
+///     use staged_sg_filter::sav_gol_dyn;
+///     let v = vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+///     let mut buf = vec![0.0; 6];
+///     sav_gol_dyn(&mut buf, &v, &fit_coeffs(2, 1, 0, 0.0));
+///     fill_fringes(&mut buf, &v, 2, 1, 0, Mode::Nearest);
+///     assert!((buf[0] - 0.6).abs() < 1e-12);
+///     fill_fringes(&mut buf, &v, 2, 1, 0, Mode::Interp);
+///     assert!((buf[0] - 0.0).abs() < 1e-12);
+///```
+pub fn fill_fringes(
+    buf: &mut [f64],
+    data: &[f64],
+    half_width: usize,
+    order: usize,
+    deriv: usize,
+    mode: Mode,
+) {
+    let (coeffs, interp) = fringe_coeffs(half_width, order, deriv, mode);
+    fill_fringes_with(buf, data, &coeffs, &interp, mode);
+}
+
+/// `fill_fringes` with the coefficient rows of `fringe_coeffs` computed ahead of time
+pub(crate) fn fill_fringes_with(
+    buf: &mut [f64],
+    data: &[f64],
+    coeffs: &[f64],
+    interp: &[Vec<f64>],
+    mode: Mode,
+) {
+    let half_width = coeffs.len() / 2;
+    let (left, right) = fringes(data.len(), |i| data[i], coeffs, interp, mode);
+    let n = buf.len();
+    buf[..half_width].copy_from_slice(&left);
+    buf[n - half_width..].copy_from_slice(&right);
+}
+
+/// `f32` version of `fill_fringes`, the fringes are computed in `f64`.
+pub fn fill_fringes_f32(
+    buf: &mut [f32],
+    data: &[f32],
+    half_width: usize,
+    order: usize,
+    deriv: usize,
+    mode: Mode,
+) {
+    let (coeffs, interp) = fringe_coeffs(half_width, order, deriv, mode);
+    fill_fringes_with_f32(buf, data, &coeffs, &interp, mode);
+}
+
+pub(crate) fn fill_fringes_with_f32(
+    buf: &mut [f32],
+    data: &[f32],
+    coeffs: &[f64],
+    interp: &[Vec<f64>],
+    mode: Mode,
+) {
+    let half_width = coeffs.len() / 2;
+    let (left, right) = fringes(data.len(), |i| data[i] as f64, coeffs, interp, mode);
+    let n = buf.len();
+    buf[..half_width]
+        .iter_mut()
+        .zip(left.iter())
+        .for_each(|(b, x)| *b = *x as f32);
+    buf[n - half_width..]
+        .iter_mut()
+        .zip(right.iter())
+        .for_each(|(b, x)| *b = *x as f32);
+}
+
+/// Coefficients the fringes are filtered with: the row of the kernels, and for `Mode::Interp`
+/// the rows evaluating the fit at each position of the left then the right fringe.
+pub(crate) fn fringe_coeffs(
+    half_width: usize,
+    order: usize,
+    deriv: usize,
+    mode: Mode,
+) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let interp = match mode {
+        Mode::Interp => (0..half_width)
+            .map(|i| i as f64 - half_width as f64)
+            .chain((1..=half_width).map(|i| i as f64))
+            .map(|x| fit_coeffs(half_width, order, deriv, x))
+            .collect(),
+        _ => vec![],
+    };
+    (fit_coeffs(half_width, order, deriv, 0.0), interp)
+}
+
+/// Left and right fringes of a signal of length `n` read through `get`
+fn fringes(
+    n: usize,
+    get: impl Fn(usize) -> f64,
+    coeffs: &[f64],
+    interp: &[Vec<f64>],
+    mode: Mode,
+) -> (Vec<f64>, Vec<f64>) {
+    let window_size = coeffs.len();
+    let half_width = window_size / 2;
+    if n < window_size {
+        panic!("data must hold at least 2 * half_width + 1 elements");
+    }
+    let apply = |window: &[f64], coeffs: &[f64]| {
+        let mut buf = 0.0;
+        dot_prod_update(&mut buf, window, coeffs);
+        buf
+    };
+    if mode == Mode::Interp {
+        let first: Vec<f64> = (0..window_size).map(&get).collect();
+        let last: Vec<f64> = (n - window_size..n).map(&get).collect();
+        let (left, right) = interp.split_at(half_width);
+        return (
+            left.iter().map(|row| apply(&first, row)).collect(),
+            right.iter().map(|row| apply(&last, row)).collect(),
+        );
+    }
+
+    // Every other mode extends the signal past its ends and filters as usual
+    let pad = |i: isize| -> f64 {
+        let last = n as isize - 1;
+        if (0..=last).contains(&i) {
+            return get(i as usize);
+        }
+        match mode {
+            Mode::Mirror if i < 0 => get(-i as usize),
+            Mode::Mirror => get((2 * last - i) as usize),
+            Mode::Nearest => get(i.clamp(0, last) as usize),
+            Mode::Constant(c) => c,
+            Mode::Wrap => get(i.rem_euclid(n as isize) as usize),
+            Mode::Interp => unreachable!(),
+        }
+    };
+    let (n, half_width) = (n as isize, half_width as isize);
+    let left: Vec<f64> = (-half_width..2 * half_width).map(pad).collect();
+    let right: Vec<f64> = (n - 2 * half_width..n + half_width).map(pad).collect();
+    (
+        left.windows(window_size)
+            .map(|w| apply(w, coeffs))
+            .collect(),
+        right
+            .windows(window_size)
+            .map(|w| apply(w, coeffs))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_mode_from_str() {
+    assert_eq!("interp".parse(), Ok(Mode::Interp));
+    assert_eq!("constant".parse(), Ok(Mode::Constant(0.0)));
+    assert_eq!("wrap".parse(), Ok(Mode::Wrap));
+    assert!("reflect".parse::<Mode>().is_err());
+}
+
+#[test]
+fn test_fill_fringes_padding() {
+    let v: Vec<f64> = (0..12).map(|i| ((i * 7) % 5) as f64).collect();
+    let padded = |mode: Mode| -> Vec<f64> {
+        let (head, tail): (Vec<f64>, Vec<f64>) = match mode {
+            Mode::Mirror => (vec![v[3], v[2], v[1]], vec![v[10], v[9], v[8]]),
+            Mode::Nearest => (vec![v[0]; 3], vec![v[11]; 3]),
+            Mode::Constant(c) => (vec![c; 3], vec![c; 3]),
+            Mode::Wrap => (v[9..].to_vec(), v[..3].to_vec()),
+            Mode::Interp => unreachable!(),
+        };
+        head.iter()
+            .chain(v.iter())
+            .chain(tail.iter())
+            .copied()
+            .collect()
+    };
+    for mode in [
+        Mode::Mirror,
+        Mode::Nearest,
+        Mode::Constant(-2.5),
+        Mode::Wrap,
+    ] {
+        for deriv in 0..2 {
+            let coeffs = fit_coeffs(3, 2, deriv, 0.0);
+            let ext = padded(mode);
+            let mut ans = vec![0.0; ext.len()];
+            crate::sav_gol_dyn(&mut ans, &ext, &coeffs);
+            let mut buf = vec![f64::NAN; 12];
+            crate::sav_gol_dyn(&mut buf, &v, &coeffs);
+            fill_fringes(&mut buf, &v, 3, 2, deriv, mode);
+            buf.iter()
+                .zip(ans[3..15].iter())
+                .for_each(|(b, a)| assert!((b - a).abs() < 1e-12, "{mode:?}"));
+        }
+    }
+}
+
+#[test]
+fn test_fill_fringes_interp() {
+    // The fit reproduces a cubic on the fringes too
+    let f = |x: f64| x * x * x - 4.0 * x * x + 2.0;
+    let df = |x: f64| 3.0 * x * x - 8.0 * x;
+    let v: Vec<f64> = (0..15).map(|i| f(i as f64)).collect();
+    let mut buf = vec![f64::NAN; 15];
+    fill_fringes(&mut buf, &v, 4, 3, 0, Mode::Interp);
+    [0, 1, 2, 3, 11, 12, 13, 14]
+        .iter()
+        .for_each(|&i| assert!((buf[i] - f(i as f64)).abs() < 1e-9));
+    assert!(buf[4..11].iter().all(|b| b.is_nan()));
+    fill_fringes(&mut buf, &v, 4, 3, 1, Mode::Interp);
+    [0, 1, 2, 3, 11, 12, 13, 14]
+        .iter()
+        .for_each(|&i| assert!((buf[i] - df(i as f64)).abs() < 1e-9));
+}
+
+#[test]
+fn test_fill_fringes_f32() {
+    let v: Vec<f32> = (0..10).map(|i| i as f32).collect();
+    let mut buf = vec![0.0f32; 10];
+    fill_fringes_f32(&mut buf, &v, 2, 1, 0, Mode::Constant(0.0));
+    assert!((buf[0] - 0.6).abs() < 1e-6);
+    assert!((buf[9] - 4.8).abs() < 1e-6);
+}
+
+#[test]
+#[should_panic(expected = "data must hold")]
+fn test_fill_fringes_short() {
+    fill_fringes(&mut [0.0; 4], &[0.0; 4], 2, 2, 0, Mode::Wrap);
+}
@@ -532,8 +532,8 @@ pub const COEFFS: [[&[f64]; 25]; 10] = [
 &[-0.01475366163580523, 0.02571269188492513, 0.01122231944322156, -0.00850416584701838, -0.019647919749905554, -0.01803073634029585, -0.007419202798785141, 0.006295198882853938, 0.017575917435660483, 0.02271667056356201, 0.02034843848715385, 0.011414338302062857, -0.001734278320905159, -0.0157609273123026, -0.027254312897989876, -0.0332644645104302, -0.03176372690837496, -0.021905306463610552, -0.004089717816626175, 0.02013791893302116, 0.04833026109804125, 0.077474641657167, 0.10438014480082056, 0.12606867876375585, 0.1401280938451589, 0.14499170794010907, 0.14011723533233858, 0.12604829195020226, 0.10435276631737414, 0.07744377316312108, 0.048300022080241915, 0.020112616068250218, -0.004106098908903922, -0.021909550792058554, -0.03175403988354584, -0.033240794307375546, -0.027217746263528412, -0.015717283457813234, -0.0016876377941827843, 0.011438138988132158, 0.020373774438425883, 0.022708024667186806, 0.017578214271658277, 0.006246228588229354, -0.007694382703451826, -0.018091381625267647, -0.019659477152400785, -0.008323631655760947, 0.012293703964951172, 0.024274219809458538, -0.01428462018550201]]];
 
 pub fn get_coeffs<const WINDOW: usize, const M: usize>() -> &'static [f64] {
-    if 25 < WINDOW {
-        panic!("WINDOW must be 0 <= WINDOW < 25");
+    if !(1..=25).contains(&WINDOW) {
+        panic!("WINDOW must be 1 <= WINDOW <= 25");
     }
     if !(1..=10).contains(&M) {
         panic!("M must be 1 <= M <= 10");
@@ -542,8 +542,8 @@ pub fn get_coeffs<const WINDOW: usize, const M: usize>() -> &'static [f64] {
     //    panic!("silly but I like it");
     //}
 
-    COEFFS[M - 1][WINDOW / 2]
-    //unsafe {*COEFFS.get_unchecked(M - 1).get_unchecked(WINDOW/2)}
+    COEFFS[M - 1][WINDOW - 1]
+    //unsafe {*COEFFS.get_unchecked(M - 1).get_unchecked(WINDOW - 1)}
 }
 
 #[test]
@@ -581,7 +581,212 @@ fn test_get_coeffs2() {
     assert_eq!(coeffs, ans);
 }
 
+#[test]
+fn test_get_coeffs_window() {
+    // Row `WINDOW - 1` holds the `2 * WINDOW + 1` taps of the window
+    (1..=25).for_each(|n| assert_eq!(COEFFS[1][n - 1].len(), 2 * n + 1));
+    assert_eq!(get_coeffs::<3, 2>().len(), 7);
+    assert_eq!(get_coeffs::<25, 4>().len(), 51);
+}
+
+#[test]
+#[should_panic(expected = "WINDOW must be")]
+fn test_get_coeffs_window0() {
+    get_coeffs::<0, 2>();
+}
+
 #[test]
 fn test_get_coeffs10() {
     get_coeffs::<1, 10>();
 }
+
+/// Coefficients of the least-squares polynomial of order `order` fitted over the
+/// `2 * half_width + 1` window positions `-half_width..=half_width`, with its `deriv`-th
+/// derivative evaluated at `x`.
+///
+/// `deriv = 0, x = 0.0` recovers the smoothing rows of `COEFFS`, anything past `half_width`
+/// extrapolates. Derivatives are per sample, divide by `delta^deriv` for a sample spacing
+/// `delta`.
+///
+/// The fit is built from the discrete orthogonal (Gram) polynomials of the window, so it stays
+/// well conditioned for every entry of the table.
+#[cfg(feature = "std")]
+pub fn fit_coeffs(half_width: usize, order: usize, deriv: usize, x: f64) -> Vec<f64> {
+    let window_size = 2 * half_width + 1;
+    if window_size <= order {
+        panic!("order must be order <= 2 * half_width");
+    }
+    let t: Vec<f64> = (0..window_size)
+        .map(|k| k as f64 - half_width as f64)
+        .collect();
+    fit_coeffs_at(&t, order, deriv, x)
+}
+
+/// `fit_coeffs` for a fit over the positions `t`, which need not be a centred window.
+/// `order` must be less than `t.len()`
+#[cfg(feature = "std")]
+pub(crate) fn fit_coeffs_at(t: &[f64], order: usize, deriv: usize, x: f64) -> Vec<f64> {
+    let window_size = t.len();
+    let mut coeffs = vec![0.0; window_size];
+    // Gram polynomials p_j evaluated on the window, and their derivatives up to `deriv` at `x`
+    let mut p_prev = vec![0.0; window_size];
+    let mut p_cur = vec![1.0; window_size];
+    let mut e_prev = vec![0.0; deriv + 1];
+    let mut e_cur = vec![0.0; deriv + 1];
+    e_cur[0] = 1.0;
+    let mut norm_prev = 1.0;
+    for j in 0..=order {
+        let norm = p_cur.iter().map(|p| p * p).sum::<f64>();
+        coeffs
+            .iter_mut()
+            .zip(p_cur.iter())
+            .for_each(|(c, p)| *c += p * e_cur[deriv] / norm);
+        if j == order {
+            break;
+        }
+        // Three term recurrence p_{j+1} = (t - a) p_j - b p_{j-1}
+        let a = t
+            .iter()
+            .zip(p_cur.iter())
+            .map(|(t, p)| t * p * p)
+            .sum::<f64>()
+            / norm;
+        let b = if j == 0 { 0.0 } else { norm / norm_prev };
+        let p_next: Vec<f64> = t
+            .iter()
+            .zip(p_cur.iter().zip(p_prev.iter()))
+            .map(|(t, (p, q))| (t - a) * p - b * q)
+            .collect();
+        // Differentiating the recurrence: p'_{j+1} = (t - a) p'_j + p_j - b p'_{j-1}
+        let e_next: Vec<f64> = (0..=deriv)
+            .map(|d| {
+                let lower = if d == 0 { 0.0 } else { d as f64 * e_cur[d - 1] };
+                (x - a) * e_cur[d] + lower - b * e_prev[d]
+            })
+            .collect();
+        p_prev = std::mem::replace(&mut p_cur, p_next);
+        e_prev = std::mem::replace(&mut e_cur, e_next);
+        norm_prev = norm;
+    }
+    coeffs
+}
+
+/// Coefficients that extrapolate the fit over a `2 * WINDOW + 1` window
+/// `horizon` samples past its last element.
+///
+/// The dot product of these with the last window of a signal predicts the sample
+/// `horizon` steps after the end of the signal.
+#[cfg(feature = "std")]
+pub fn get_predict_coeffs<const WINDOW: usize, const M: usize>(horizon: usize) -> Vec<f64> {
+    fit_coeffs(WINDOW, M, 0, (WINDOW + horizon) as f64)
+}
+
+/// Coefficients that evaluate the fit over a `2 * WINDOW + 1` window `offset` samples away
+/// from its centre.
+///
+/// Fractional offsets interpolate between samples, e.g. `0.37` aligns a signal with one
+/// sampled `0.37` samples later.
+#[cfg(feature = "std")]
+pub fn get_offset_coeffs<const WINDOW: usize, const M: usize>(offset: f64) -> Vec<f64> {
+    fit_coeffs(WINDOW, M, 0, offset)
+}
+
+/// Coefficients of the `deriv`-th derivative of the fit over a `2 * WINDOW + 1` window,
+/// evaluated at its centre.
+///
+/// `deriv = 0` gives the smoothing row, `deriv = 1` the slope per sample and so on.
+/// Derivatives past `M` are `0`.
+#[cfg(feature = "std")]
+pub fn get_deriv_coeffs<const WINDOW: usize, const M: usize>(deriv: usize) -> Vec<f64> {
+    fit_coeffs(WINDOW, M, deriv, 0.0)
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_fit_coeffs_table() {
+    // The Julia table loses accuracy from order 6 onwards, so only compare the low orders
+    for m in 1..=5 {
+        for n in 1..=25 {
+            if 2 * n < m {
+                continue;
+            }
+            let coeffs = fit_coeffs(n, m, 0, 0.0);
+            let table = COEFFS[m - 1][n - 1];
+            assert_eq!(coeffs.len(), table.len());
+            coeffs
+                .iter()
+                .zip(table.iter())
+                .for_each(|(a, b)| assert!((a - b).abs() < 1e-9, "{n} {m}: {a} != {b}"));
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_fit_coeffs_high_order() {
+    // Exact rational solution of the normal equations
+    let coeffs = fit_coeffs(25, 10, 0, 0.0);
+    let ans = [
+        -0.014487346887009214,
+        0.02474921759864074,
+        0.012250613649795725,
+    ];
+    coeffs
+        .iter()
+        .zip(ans.iter())
+        .for_each(|(a, b)| assert!((a - b).abs() < 1e-12));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_get_predict_coeffs() {
+    // A line through the window extrapolates linearly
+    let coeffs = get_predict_coeffs::<2, 1>(1);
+    let res = coeffs
+        .iter()
+        .zip([1.0, 2.0, 3.0, 4.0, 5.0])
+        .map(|(c, x)| c * x)
+        .sum::<f64>();
+    assert!((res - 6.0).abs() < 1e-12);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_get_offset_coeffs() {
+    // Interpolates a quadratic between the samples
+    let coeffs = get_offset_coeffs::<2, 2>(0.37);
+    let res = coeffs
+        .iter()
+        .zip([-2.0f64, -1.0, 0.0, 1.0, 2.0])
+        .map(|(c, x)| c * x * x)
+        .sum::<f64>();
+    assert!((res - 0.37 * 0.37).abs() < 1e-12);
+    assert_eq!(get_offset_coeffs::<2, 2>(0.0).len(), 5);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_get_deriv_coeffs() {
+    // Classic 5 point quadratic first derivative: [-2, -1, 0, 1, 2] / 10
+    let coeffs = get_deriv_coeffs::<2, 2>(1);
+    let ans = [-0.2, -0.1, 0.0, 0.1, 0.2];
+    coeffs
+        .iter()
+        .zip(ans.iter())
+        .for_each(|(a, b)| assert!((a - b).abs() < 1e-12));
+    // 5 point quadratic second derivative: [2, -1, -2, -1, 2] / 7
+    let coeffs = get_deriv_coeffs::<2, 2>(2);
+    let ans = [2.0 / 7.0, -1.0 / 7.0, -2.0 / 7.0, -1.0 / 7.0, 2.0 / 7.0];
+    coeffs
+        .iter()
+        .zip(ans.iter())
+        .for_each(|(a, b)| assert!((a - b).abs() < 1e-12));
+    assert!(get_deriv_coeffs::<2, 2>(3).iter().all(|c| c.abs() < 1e-12));
+}
+
+#[cfg(feature = "std")]
+#[test]
+#[should_panic(expected = "order must be")]
+fn test_fit_coeffs_underdetermined() {
+    fit_coeffs(1, 3, 0, 0.0);
+}
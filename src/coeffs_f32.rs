@@ -512,8 +512,8 @@ pub const COEFFS_F32: [[&[f32]; 25]; 10] = [
 &[-0.01475366163580523, 0.02571269188492513, 0.01122231944322156, -0.00850416584701838, -0.019647919749905554, -0.01803073634029585, -0.007419202798785141, 0.006295198882853938, 0.017575917435660483, 0.02271667056356201, 0.02034843848715385, 0.011414338302062857, -0.001734278320905159, -0.0157609273123026, -0.027254312897989876, -0.0332644645104302, -0.03176372690837496, -0.021905306463610552, -0.004089717816626175, 0.02013791893302116, 0.04833026109804125, 0.077474641657167, 0.10438014480082056, 0.12606867876375585, 0.1401280938451589, 0.14499170794010907, 0.14011723533233858, 0.12604829195020226, 0.10435276631737414, 0.07744377316312108, 0.048300022080241915, 0.020112616068250218, -0.004106098908903922, -0.021909550792058554, -0.03175403988354584, -0.033240794307375546, -0.027217746263528412, -0.015717283457813234, -0.0016876377941827843, 0.011438138988132158, 0.020373774438425883, 0.022708024667186806, 0.017578214271658277, 0.006246228588229354, -0.007694382703451826, -0.018091381625267647, -0.019659477152400785, -0.008323631655760947, 0.012293703964951172, 0.024274219809458538, -0.01428462018550201]]];
 
 pub fn get_coeffs_f32<const WINDOW: usize, const M: usize>() -> &'static [f32] {
-    if 25 < WINDOW {
-        panic!("WINDOW must be 0 <= WINDOW < 25");
+    if !(1..=25).contains(&WINDOW) {
+        panic!("WINDOW must be 1 <= WINDOW <= 25");
     }
     if !(1..=10).contains(&M) {
         panic!("M must be 1 <= M <= 10");
@@ -522,8 +522,8 @@ pub fn get_coeffs_f32<const WINDOW: usize, const M: usize>() -> &'static [f32] {
     //    panic!("silly but I like it");
     //}
 
-    COEFFS_F32[M - 1][WINDOW / 2]
-    //unsafe {*COEFFS.get_unchecked(M - 1).get_unchecked(WINDOW/2)}
+    COEFFS_F32[M - 1][WINDOW - 1]
+    //unsafe {*COEFFS.get_unchecked(M - 1).get_unchecked(WINDOW - 1)}
 }
 
 #[test]
@@ -569,6 +569,20 @@ fn test_get_coeffs2_f32() {
     assert_eq!(coeffs, ans);
 }
 
+#[test]
+fn test_get_coeffs_window_f32() {
+    // Row `WINDOW - 1` holds the `2 * WINDOW + 1` taps of the window
+    (1..=25).for_each(|n| assert_eq!(COEFFS_F32[1][n - 1].len(), 2 * n + 1));
+    assert_eq!(get_coeffs_f32::<3, 2>().len(), 7);
+    assert_eq!(get_coeffs_f32::<25, 4>().len(), 51);
+}
+
+#[test]
+#[should_panic(expected = "WINDOW must be")]
+fn test_get_coeffs_window0_f32() {
+    get_coeffs_f32::<0, 2>();
+}
+
 #[test]
 fn test_get_coeffs10() {
     get_coeffs_f32::<1, 10>();
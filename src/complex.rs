@@ -0,0 +1,178 @@
+//! Filters for complex signals, e.g. I/Q samples or spectra.
+//!
+//! The coefficients are real, so the real and imaginary parts are smoothed independently, but in
+//! a single pass: `sav_gol_re_im` works on interleaved `re, im` slices and accumulates both parts
+//! of a window side by side, which vectorizes over the interleaved layout. Each part goes through
+//! the same dot product as `sav_gol`, so the output is bit for bit that of filtering the parts
+//! separately. With the `complex` feature, `sav_gol_complex` takes `num_complex::Complex` slices.
+
+use crate::coeffs::get_coeffs;
+use crate::coeffs_f32::get_coeffs_f32;
+use crate::{mul_add_f32, mul_add_f64};
+
+#[inline]
+fn dot_prod_re_im(data: &[f64], coeffs: &[f64]) -> [f64; 2] {
+    data.chunks_exact(2)
+        .zip(coeffs.iter())
+        .fold([0.0f64; 2], |[re, im], (x, c)| {
+            [mul_add_f64(x[0], *c, re), mul_add_f64(x[1], *c, im)]
+        })
+}
+
+#[inline]
+fn dot_prod_re_im_f32(data: &[f32], coeffs: &[f32]) -> [f32; 2] {
+    data.chunks_exact(2)
+        .zip(coeffs.iter())
+        .fold([0.0f32; 2], |[re, im], (x, c)| {
+            [mul_add_f32(x[0], *c, re), mul_add_f32(x[1], *c, im)]
+        })
+}
+
+/// `sav_gol` on complex samples stored as interleaved `re, im` pairs.
+///
+/// As with `sav_gol`, the fringes of `buf` are left untouched.
+///
+/// # Panics
+/// If `data` holds an odd number of elements.
+/// ```
+///     use staged_sg_filter::complex::sav_gol_re_im;
+///     // 0, 3i, 0, 3i, 0
+///     let v = [0.0, 0.0, 0.0, 3.0, 0.0, 0.0, 0.0, 3.0, 0.0, 0.0];
+///     let mut buf = [0.0; 10];
+///     sav_gol_re_im::<1, 1>(&mut buf, &v);
+///     assert!((buf[4] - 0.0).abs() < 1e-12);
+///     assert!((buf[5] - 2.0).abs() < 1e-12);
+///```
+pub fn sav_gol_re_im<const WINDOW: usize, const M: usize>(buf: &mut [f64], data: &[f64]) {
+    if !data.len().is_multiple_of(2) {
+        panic!("data must hold interleaved re, im pairs");
+    }
+    let coeffs = get_coeffs::<WINDOW, M>();
+    let window_size = 2 * WINDOW + 1;
+    let body_size = data.len() / 2 - (window_size - 1);
+    buf.chunks_exact_mut(2)
+        // Start the iteration without the `windows` reaching before `buf` starts
+        .skip(window_size / 2)
+        .zip(data.windows(2 * window_size).step_by(2))
+        // Advance `body_size` iterations so that `windows` doesn't go over the end of `buf`
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            let [re, im] = dot_prod_re_im(data, coeffs);
+            buf[0] = re;
+            buf[1] = im;
+        });
+}
+
+pub fn sav_gol_re_im_f32<const WINDOW: usize, const M: usize>(buf: &mut [f32], data: &[f32]) {
+    if !data.len().is_multiple_of(2) {
+        panic!("data must hold interleaved re, im pairs");
+    }
+    let coeffs = get_coeffs_f32::<WINDOW, M>();
+    let window_size = 2 * WINDOW + 1;
+    let body_size = data.len() / 2 - (window_size - 1);
+    buf.chunks_exact_mut(2)
+        // Start the iteration without the `windows` reaching before `buf` starts
+        .skip(window_size / 2)
+        .zip(data.windows(2 * window_size).step_by(2))
+        // Advance `body_size` iterations so that `windows` doesn't go over the end of `buf`
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            let [re, im] = dot_prod_re_im_f32(data, coeffs);
+            buf[0] = re;
+            buf[1] = im;
+        });
+}
+
+/// `sav_gol` on `Complex<f64>` samples, see `sav_gol_re_im`.
+/// ```
+///     use num_complex::Complex;
+///     use staged_sg_filter::complex::sav_gol_complex;
+///     let v: Vec<Complex<f64>> = (0..7).map(|i| Complex::new(i as f64, (i * i) as f64)).collect();
+///     let mut buf = vec![Complex::new(0.0, 0.0); 7];
+///     sav_gol_complex::<2, 2>(&mut buf, &v);
+///     assert!((buf[3].re - 3.0).abs() < 1e-9 && (buf[3].im - 9.0).abs() < 1e-9);
+///```
+#[cfg(feature = "complex")]
+pub fn sav_gol_complex<const WINDOW: usize, const M: usize>(
+    buf: &mut [num_complex::Complex<f64>],
+    data: &[num_complex::Complex<f64>],
+) {
+    // SAFETY: `Complex<T>` is `#[repr(C)]` with the fields `re` and `im`, so a slice of them is a
+    // slice of interleaved `re, im` pairs of twice the length
+    let (buf, data) = unsafe {
+        (
+            core::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut f64, 2 * buf.len()),
+            core::slice::from_raw_parts(data.as_ptr() as *const f64, 2 * data.len()),
+        )
+    };
+    sav_gol_re_im::<WINDOW, M>(buf, data);
+}
+
+#[cfg(feature = "complex")]
+pub fn sav_gol_complex_f32<const WINDOW: usize, const M: usize>(
+    buf: &mut [num_complex::Complex<f32>],
+    data: &[num_complex::Complex<f32>],
+) {
+    // SAFETY: as in `sav_gol_complex`
+    let (buf, data) = unsafe {
+        (
+            core::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut f32, 2 * buf.len()),
+            core::slice::from_raw_parts(data.as_ptr() as *const f32, 2 * data.len()),
+        )
+    };
+    sav_gol_re_im_f32::<WINDOW, M>(buf, data);
+}
+
+#[test]
+fn test_sav_gol_re_im() {
+    use crate::{sav_gol, sav_gol_f32};
+    let re: Vec<f64> = (0..80).map(|i| (i as f64 * 0.2).cos()).collect();
+    let im: Vec<f64> = (0..80).map(|i| ((i * 5) % 7) as f64 - 3.0).collect();
+    let v: Vec<f64> = re
+        .iter()
+        .zip(im.iter())
+        .flat_map(|(r, i)| [*r, *i])
+        .collect();
+    let mut buf = vec![f64::NAN; 160];
+    sav_gol_re_im::<6, 3>(&mut buf, &v);
+    for (ch, part) in [&re, &im].iter().enumerate() {
+        let mut ans = vec![f64::NAN; 80];
+        sav_gol::<6, 3>(&mut ans, part);
+        let res: Vec<f64> = buf.iter().skip(ch).step_by(2).copied().collect();
+        assert_eq!(res[6..74], ans[6..74]);
+        assert!(res[..6].iter().chain(&res[74..]).all(|r| r.is_nan()));
+    }
+
+    let v: Vec<f32> = v.iter().map(|&x| x as f32).collect();
+    let mut buf = vec![0.0f32; 160];
+    sav_gol_re_im_f32::<2, 2>(&mut buf, &v);
+    for ch in 0..2 {
+        let part: Vec<f32> = v.iter().skip(ch).step_by(2).copied().collect();
+        let mut ans = vec![0.0f32; 80];
+        sav_gol_f32::<2, 2>(&mut ans, &part);
+        let res: Vec<f32> = buf.iter().skip(ch).step_by(2).copied().collect();
+        assert_eq!(res, ans);
+    }
+}
+
+#[test]
+#[should_panic(expected = "re, im pairs")]
+fn test_sav_gol_re_im_odd() {
+    sav_gol_re_im::<1, 1>(&mut [0.0; 7], &[0.0; 7]);
+}
+
+#[cfg(feature = "complex")]
+#[test]
+fn test_sav_gol_complex() {
+    use num_complex::Complex;
+    let v: Vec<Complex<f32>> = (0..40)
+        .map(|i| Complex::new((i as f32 * 0.3).sin(), (i % 4) as f32))
+        .collect();
+    let flat: Vec<f32> = v.iter().flat_map(|z| [z.re, z.im]).collect();
+    let mut ans = vec![0.0f32; 80];
+    sav_gol_re_im_f32::<3, 2>(&mut ans, &flat);
+    let mut buf = vec![Complex::new(0.0f32, 0.0); 40];
+    sav_gol_complex_f32::<3, 2>(&mut buf, &v);
+    let res: Vec<f32> = buf.iter().flat_map(|z| [z.re, z.im]).collect();
+    assert_eq!(res, ans);
+}
@@ -0,0 +1,178 @@
+//! Automatic window and order selection by cross-validation.
+//!
+//! A Savitzky-Golay filter is a linear smoother `y_hat = H y`, so the leave-one-out residual of
+//! every sample can be read off the ordinary residual and the leverage `H_ii`, which for every
+//! sample in the body of the signal is just the centre tap of the coefficient row. Scoring a
+//! `(half_width, order)` pair therefore costs a single filter pass.
+
+use crate::coeffs::fit_coeffs;
+use crate::dot_prod_update;
+
+/// Cross-validation error used to rank the candidates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Criterion {
+    /// Mean squared leave-one-out residual `(y_i - y_hat_i) / (1 - H_ii)` over the body of the signal
+    LeaveOneOut,
+    /// Generalized cross-validation `RSS / N / (1 - tr(H) / N)^2`, counting the untouched fringes
+    /// as samples reproduced exactly
+    Generalized,
+}
+
+/// Half-widths and orders to search over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchGrid {
+    pub half_widths: Vec<usize>,
+    pub orders: Vec<usize>,
+}
+
+/// Defaults to the pairs precomputed in `COEFFS`.
+impl Default for SearchGrid {
+    fn default() -> Self {
+        SearchGrid {
+            half_widths: (1..=25).collect(),
+            orders: (1..=10).collect(),
+        }
+    }
+}
+
+/// Score of a single candidate of the grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Score {
+    pub half_width: usize,
+    pub order: usize,
+    pub score: f64,
+}
+
+/// Chosen parameters along with every score computed on the way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub half_width: usize,
+    pub order: usize,
+    pub score: f64,
+    /// Scores of all candidates that fit in `data`, in grid order
+    pub table: Vec<Score>,
+}
+
+/// Cross-validation score of smoothing `data` with a `(half_width, order)` filter.
+///
+/// Returns `None` when the window doesn't fit in `data` or the fit interpolates every sample
+/// (`order >= 2 * half_width`), which leaves no residual to validate against.
+pub fn score(data: &[f64], half_width: usize, order: usize, criterion: Criterion) -> Option<f64> {
+    let window_size = 2 * half_width + 1;
+    if data.len() < window_size || 2 * half_width <= order {
+        return None;
+    }
+    let coeffs = fit_coeffs(half_width, order, 0, 0.0);
+    let leverage = coeffs[half_width];
+    let rss = data
+        .windows(window_size)
+        .zip(data.iter().skip(half_width))
+        .map(|(window, y)| {
+            let mut y_hat = 0.0;
+            dot_prod_update(&mut y_hat, window, &coeffs);
+            (y - y_hat) * (y - y_hat)
+        })
+        .sum::<f64>();
+    let body_size = (data.len() - (window_size - 1)) as f64;
+    let n = data.len() as f64;
+    Some(match criterion {
+        Criterion::LeaveOneOut => rss / body_size / ((1.0 - leverage) * (1.0 - leverage)),
+        Criterion::Generalized => {
+            let trace = body_size * leverage + (n - body_size);
+            rss / n / ((1.0 - trace / n) * (1.0 - trace / n))
+        }
+    })
+}
+
+/// Picks the half-width and order of `grid` minimizing `criterion` on `data`.
+///
+/// Returns `None` if no candidate of the grid can be scored on `data`.
+/// ```
+///     use staged_sg_filter::crossval::{optimize, Criterion, SearchGrid};
+///     let v: Vec<f64> = (0..200)
+///         .map(|i| (i as f64 * 0.05).sin() + if i % 2 == 0 { 0.1 } else { -0.1 })
+///         .collect();
+///     let best = optimize(&v, &SearchGrid::default(), Criterion::LeaveOneOut).unwrap();
+///     assert!(best.half_width > 1);
+///     assert!(best.table.iter().all(|s| s.score >= best.score));
+///```
+pub fn optimize(data: &[f64], grid: &SearchGrid, criterion: Criterion) -> Option<Selection> {
+    let table: Vec<Score> = grid
+        .half_widths
+        .iter()
+        .flat_map(|&half_width| grid.orders.iter().map(move |&order| (half_width, order)))
+        .filter_map(|(half_width, order)| {
+            score(data, half_width, order, criterion).map(|score| Score {
+                half_width,
+                order,
+                score,
+            })
+        })
+        .collect();
+    let best = *table.iter().min_by(|a, b| a.score.total_cmp(&b.score))?;
+    Some(Selection {
+        half_width: best.half_width,
+        order: best.order,
+        score: best.score,
+        table,
+    })
+}
+
+#[cfg(test)]
+fn noise(n: usize) -> Vec<f64> {
+    crate::test_util::noise(0x2545_f491_4f6c_dd1d, n)
+}
+
+#[test]
+fn test_score_leave_one_out() {
+    // A linear fit over 5 samples without the centre one predicts the mean of its neighbours
+    let v = noise(30);
+    let ans = v
+        .windows(5)
+        .map(|w| {
+            let r = w[2] - (w[0] + w[1] + w[3] + w[4]) / 4.0;
+            r * r
+        })
+        .sum::<f64>()
+        / 26.0;
+    let res = score(&v, 2, 1, Criterion::LeaveOneOut).unwrap();
+    assert!((res - ans).abs() < 1e-12);
+}
+
+#[test]
+fn test_score_invalid() {
+    let v = noise(10);
+    assert_eq!(score(&v, 5, 2, Criterion::LeaveOneOut), None);
+    assert_eq!(score(&v, 1, 2, Criterion::Generalized), None);
+    assert!(score(&v, 4, 2, Criterion::Generalized).is_some());
+}
+
+#[test]
+fn test_optimize() {
+    let n = 500;
+    let v: Vec<f64> = noise(n)
+        .iter()
+        .enumerate()
+        .map(|(i, e)| (i as f64 * 0.02).sin() + 0.2 * e)
+        .collect();
+    for criterion in [Criterion::LeaveOneOut, Criterion::Generalized] {
+        let best = optimize(&v, &SearchGrid::default(), criterion).unwrap();
+        assert_eq!(best.table.len(), 25 * 10 - (9 + 7 + 5 + 3 + 1));
+        assert!(best.table.iter().all(|s| s.score >= best.score));
+        // A slow sine under white noise wants a wide window
+        assert!(best.half_width >= 5);
+        assert_eq!(
+            Some(best.score),
+            score(&v, best.half_width, best.order, criterion)
+        );
+    }
+
+    let grid = SearchGrid {
+        half_widths: vec![2, 3],
+        orders: vec![2],
+    };
+    let best = optimize(&v, &grid, Criterion::LeaveOneOut).unwrap();
+    assert_eq!(best.table.len(), 2);
+    assert_eq!(best.order, 2);
+    assert_eq!(optimize(&v[..3], &grid, Criterion::LeaveOneOut), None);
+}
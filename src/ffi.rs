@@ -0,0 +1,283 @@
+//! C ABI for calling the filters from C, C++ or Fortran (through `ISO_C_BINDING`).
+//!
+//! The declarations are in `include/staged_sg_filter.h`, and the `ffi` workspace member builds
+//! them as a `cdylib` and a `staticlib` to link against. Every function validates its arguments
+//! and returns one of the `SGF_*` status codes instead of panicking, and any panic that still
+//! happens is caught before it can unwind into the caller.
+
+use crate::boundary::{fill_fringes, fill_fringes_f32, Mode};
+use crate::coeffs::fit_coeffs;
+use crate::{sav_gol_dyn, sav_gol_dyn_f32};
+use std::ffi::{c_char, c_int};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+pub const SGF_OK: c_int = 0;
+/// `in` or `out` is a null pointer
+pub const SGF_ERR_NULL: c_int = -1;
+/// `half_width` is 0
+pub const SGF_ERR_WINDOW: c_int = -2;
+/// `order` is larger than `2 * half_width`
+pub const SGF_ERR_ORDER: c_int = -3;
+/// `n` is smaller than the window, `2 * half_width + 1`
+pub const SGF_ERR_LENGTH: c_int = -4;
+/// `mode` is not one of the `SGF_MODE_*` constants
+pub const SGF_ERR_MODE: c_int = -5;
+/// A panic was caught, which is a bug in this crate
+pub const SGF_ERR_INTERNAL: c_int = -99;
+
+pub const SGF_MODE_INTERP: c_int = 0;
+pub const SGF_MODE_MIRROR: c_int = 1;
+pub const SGF_MODE_NEAREST: c_int = 2;
+/// Pads with `0.0`
+pub const SGF_MODE_CONSTANT: c_int = 3;
+pub const SGF_MODE_WRAP: c_int = 4;
+
+fn mode_from_c(mode: c_int) -> Option<Mode> {
+    match mode {
+        SGF_MODE_INTERP => Some(Mode::Interp),
+        SGF_MODE_MIRROR => Some(Mode::Mirror),
+        SGF_MODE_NEAREST => Some(Mode::Nearest),
+        SGF_MODE_CONSTANT => Some(Mode::Constant(0.0)),
+        SGF_MODE_WRAP => Some(Mode::Wrap),
+        _ => None,
+    }
+}
+
+/// Checks the arguments shared by every entry point and runs `filter` on the validated slices,
+/// catching panics. `in` and `out` may be the same pointer, the input is copied first then.
+///
+/// # Safety
+/// Unless null, `input` must be valid for `n` reads and `out` for `n` writes, and they must
+/// either not overlap or be equal.
+unsafe fn checked<T: Copy>(
+    input: *const T,
+    out: *mut T,
+    n: usize,
+    half_width: u32,
+    order: u32,
+    mode: c_int,
+    filter: impl FnOnce(&mut [T], &[T], usize, usize, Mode),
+) -> c_int {
+    if input.is_null() || out.is_null() {
+        return SGF_ERR_NULL;
+    }
+    let (half_width, order) = (half_width as usize, order as usize);
+    if half_width == 0 {
+        return SGF_ERR_WINDOW;
+    }
+    // A window that doesn't even fit in `usize` is longer than any input
+    let Some(window_size) = half_width.checked_mul(2).and_then(|w| w.checked_add(1)) else {
+        return SGF_ERR_LENGTH;
+    };
+    if order >= window_size {
+        return SGF_ERR_ORDER;
+    }
+    if n < window_size {
+        return SGF_ERR_LENGTH;
+    }
+    let Some(mode) = mode_from_c(mode) else {
+        return SGF_ERR_MODE;
+    };
+    let res = catch_unwind(AssertUnwindSafe(|| {
+        let copy;
+        let data = match std::ptr::eq(input, out) {
+            true => {
+                copy = std::slice::from_raw_parts(input, n).to_vec();
+                copy.as_slice()
+            }
+            false => std::slice::from_raw_parts(input, n),
+        };
+        let buf = std::slice::from_raw_parts_mut(out, n);
+        filter(buf, data, half_width, order, mode);
+    }));
+    match res {
+        Ok(()) => SGF_OK,
+        Err(_) => SGF_ERR_INTERNAL,
+    }
+}
+
+/// Writes the `deriv`-th derivative (per sample) of the order `order` fit over
+/// `2 * half_width + 1` samples of `input` to `out`, with the fringes handled by `mode`.
+///
+/// Half-widths up to 25 run on the const generic kernels. A `deriv` above `order` writes zeros,
+/// the derivative of the fit.
+///
+/// # Safety
+/// Unless null, `input` must be valid for `n` reads and `out` for `n` writes. They must either
+/// not overlap or be the same pointer, to filter in place.
+#[no_mangle]
+pub unsafe extern "C" fn sgf_filter_deriv_f64(
+    input: *const f64,
+    out: *mut f64,
+    n: usize,
+    half_width: u32,
+    order: u32,
+    deriv: u32,
+    mode: c_int,
+) -> c_int {
+    checked(
+        input,
+        out,
+        n,
+        half_width,
+        order,
+        mode,
+        |buf, data, w, m, mode| {
+            if deriv as usize > m {
+                buf.fill(0.0);
+                return;
+            }
+            sav_gol_dyn(buf, data, &fit_coeffs(w, m, deriv as usize, 0.0));
+            fill_fringes(buf, data, w, m, deriv as usize, mode);
+        },
+    )
+}
+
+/// `f32` version of `sgf_filter_deriv_f64`.
+///
+/// # Safety
+/// See `sgf_filter_deriv_f64`.
+#[no_mangle]
+pub unsafe extern "C" fn sgf_filter_deriv_f32(
+    input: *const f32,
+    out: *mut f32,
+    n: usize,
+    half_width: u32,
+    order: u32,
+    deriv: u32,
+    mode: c_int,
+) -> c_int {
+    checked(
+        input,
+        out,
+        n,
+        half_width,
+        order,
+        mode,
+        |buf, data, w, m, mode| {
+            if deriv as usize > m {
+                buf.fill(0.0);
+                return;
+            }
+            let coeffs: Vec<f32> = fit_coeffs(w, m, deriv as usize, 0.0)
+                .iter()
+                .map(|&c| c as f32)
+                .collect();
+            sav_gol_dyn_f32(buf, data, &coeffs);
+            fill_fringes_f32(buf, data, w, m, deriv as usize, mode);
+        },
+    )
+}
+
+/// Smooths `input` into `out`, see `sgf_filter_deriv_f64`.
+///
+/// # Safety
+/// See `sgf_filter_deriv_f64`.
+#[no_mangle]
+pub unsafe extern "C" fn sgf_filter_f64(
+    input: *const f64,
+    out: *mut f64,
+    n: usize,
+    half_width: u32,
+    order: u32,
+    mode: c_int,
+) -> c_int {
+    sgf_filter_deriv_f64(input, out, n, half_width, order, 0, mode)
+}
+
+/// Smooths `input` into `out`, see `sgf_filter_deriv_f64`.
+///
+/// # Safety
+/// See `sgf_filter_deriv_f64`.
+#[no_mangle]
+pub unsafe extern "C" fn sgf_filter_f32(
+    input: *const f32,
+    out: *mut f32,
+    n: usize,
+    half_width: u32,
+    order: u32,
+    mode: c_int,
+) -> c_int {
+    sgf_filter_deriv_f32(input, out, n, half_width, order, 0, mode)
+}
+
+/// Static, nul-terminated description of a status code.
+#[no_mangle]
+pub extern "C" fn sgf_strerror(code: c_int) -> *const c_char {
+    let msg: &'static [u8] = match code {
+        SGF_OK => b"success\0",
+        SGF_ERR_NULL => b"null pointer argument\0",
+        SGF_ERR_WINDOW => b"half_width must be at least 1\0",
+        SGF_ERR_ORDER => b"order must be at most 2 * half_width\0",
+        SGF_ERR_LENGTH => b"n must be at least 2 * half_width + 1\0",
+        SGF_ERR_MODE => b"unknown boundary mode\0",
+        SGF_ERR_INTERNAL => b"internal error\0",
+        _ => b"unknown status code\0",
+    };
+    msg.as_ptr() as *const c_char
+}
+
+#[test]
+fn test_sgf_filter() {
+    let v: Vec<f64> = (0..30).map(|i| (i as f64 * 0.2).sin()).collect();
+    let mut out = vec![0.0; 30];
+    let code = unsafe { sgf_filter_f64(v.as_ptr(), out.as_mut_ptr(), 30, 4, 3, SGF_MODE_MIRROR) };
+    assert_eq!(code, SGF_OK);
+    let mut ans = vec![0.0; 30];
+    sav_gol_dyn(&mut ans, &v, &fit_coeffs(4, 3, 0, 0.0));
+    fill_fringes(&mut ans, &v, 4, 3, 0, Mode::Mirror);
+    assert_eq!(out, ans);
+
+    // In place
+    let mut w: Vec<f32> = v.iter().map(|&x| x as f32).collect();
+    let mut ans = vec![0.0f32; 30];
+    let code = unsafe { sgf_filter_deriv_f32(w.as_ptr(), ans.as_mut_ptr(), 30, 4, 3, 1, 0) };
+    assert_eq!(code, SGF_OK);
+    let ptr = w.as_mut_ptr();
+    assert_eq!(
+        unsafe { sgf_filter_deriv_f32(ptr, ptr, 30, 4, 3, 1, 0) },
+        SGF_OK
+    );
+    assert_eq!(w, ans);
+}
+
+#[test]
+fn test_sgf_errors() {
+    let v = [1.0; 8];
+    let call = |input: *const f64, n, half_width, order, mode| {
+        let mut out = [0.0; 8];
+        unsafe { sgf_filter_f64(input, out.as_mut_ptr(), n, half_width, order, mode) }
+    };
+    assert_eq!(call(std::ptr::null(), 8, 2, 2, 0), SGF_ERR_NULL);
+    assert_eq!(call(v.as_ptr(), 8, 0, 0, 0), SGF_ERR_WINDOW);
+    assert_eq!(call(v.as_ptr(), 8, 2, 5, 0), SGF_ERR_ORDER);
+    assert_eq!(call(v.as_ptr(), 8, 4, 2, 0), SGF_ERR_LENGTH);
+    assert_eq!(call(v.as_ptr(), 8, 2, 2, 7), SGF_ERR_MODE);
+    assert_eq!(
+        unsafe { sgf_filter_f64(v.as_ptr(), std::ptr::null_mut(), 8, 2, 2, 0) },
+        SGF_ERR_NULL
+    );
+    // A window longer than any input is rejected before anything is allocated for it
+    assert_eq!(call(v.as_ptr(), 8, u32::MAX, 2, 0), SGF_ERR_LENGTH);
+    let msg = unsafe { std::ffi::CStr::from_ptr(sgf_strerror(SGF_ERR_ORDER)) };
+    assert_eq!(msg.to_str(), Ok("order must be at most 2 * half_width"));
+}
+
+#[test]
+fn test_sgf_filter_deriv_above_order() {
+    // The derivatives of a quadratic fit past the second are zero, without fitting them
+    let v: Vec<f64> = (0..20).map(|i| (i * i) as f64).collect();
+    for deriv in [3, 40, u32::MAX] {
+        let mut out = vec![f64::NAN; 20];
+        let code =
+            unsafe { sgf_filter_deriv_f64(v.as_ptr(), out.as_mut_ptr(), 20, 3, 2, deriv, 0) };
+        assert_eq!(code, SGF_OK);
+        assert_eq!(out, vec![0.0; 20]);
+        let w = [1.0f32; 20];
+        let mut out = vec![f32::NAN; 20];
+        let code =
+            unsafe { sgf_filter_deriv_f32(w.as_ptr(), out.as_mut_ptr(), 20, 3, 2, deriv, 0) };
+        assert_eq!(code, SGF_OK);
+        assert_eq!(out, vec![0.0f32; 20]);
+    }
+}
@@ -0,0 +1,189 @@
+//! FFT convolution for very wide windows.
+//!
+//! The direct kernels cost one multiply-add per coefficient and output, which adds up once a row
+//! is hundreds of points long. `sav_gol_fft` filters by overlap-save instead: the signal is cut
+//! into overlapping blocks of the transform size, and each block is transformed, multiplied by
+//! the transform of the reversed row and transformed back, leaving `size - window_size + 1`
+//! outputs per block at a cost logarithmic in the transform size. The row is real, so two
+//! consecutive blocks share one complex transform as its real and imaginary parts.
+//!
+//! `sav_gol_dyn` switches to `sav_gol_fft` for rows of `CROSSOVER` points and more. The results
+//! differ from the direct kernel by a few ulps of the largest sample times the row's norm.
+
+/// Shortest row `sav_gol_dyn` filters with `sav_gol_fft`, measured with the `savgol_dyn_*`
+/// benchmarks in `benches/divan.rs`
+pub const CROSSOVER: usize = 53;
+
+/// Radix-2 transform of a fixed power of two size
+struct Fft {
+    /// `exp(-2 pi i k / size)` for `k < size / 2`
+    twiddles: Vec<[f64; 2]>,
+}
+
+impl Fft {
+    fn new(size: usize) -> Self {
+        let twiddles = (0..size / 2)
+            .map(|k| {
+                let phase = -2.0 * std::f64::consts::PI * k as f64 / size as f64;
+                [phase.cos(), phase.sin()]
+            })
+            .collect();
+        Fft { twiddles }
+    }
+
+    /// In-place forward transform, the inverse is `conj(forward(conj(x))) / size`
+    fn forward(&self, x: &mut [[f64; 2]]) {
+        let size = x.len();
+        let bits = size.trailing_zeros();
+        for i in 0..size {
+            let j = i.reverse_bits() >> (usize::BITS - bits);
+            if i < j {
+                x.swap(i, j);
+            }
+        }
+        let mut half = 1;
+        while half < size {
+            let stride = size / (2 * half);
+            x.chunks_exact_mut(2 * half).for_each(|chunk| {
+                let (lo, hi) = chunk.split_at_mut(half);
+                lo.iter_mut()
+                    .zip(hi.iter_mut())
+                    .zip(self.twiddles.iter().step_by(stride))
+                    .for_each(|((a, b), w)| {
+                        let t = [b[0] * w[0] - b[1] * w[1], b[0] * w[1] + b[1] * w[0]];
+                        *b = [a[0] - t[0], a[1] - t[1]];
+                        *a = [a[0] + t[0], a[1] + t[1]];
+                    });
+            });
+            half *= 2;
+        }
+    }
+}
+
+/// Transform size for a row of `window_size` points, long enough that most of each block is
+/// output
+fn fft_size(window_size: usize) -> usize {
+    (8 * window_size).next_power_of_two()
+}
+
+/// Overlap-save on samples that widen to `f64` with `to_f64`, narrowed back with `from_f64`
+fn overlap_save<T: Copy>(
+    buf: &mut [T],
+    data: &[T],
+    coeffs: &[f64],
+    to_f64: impl Fn(T) -> f64,
+    from_f64: impl Fn(f64) -> T,
+) {
+    if coeffs.len().is_multiple_of(2) {
+        panic!("coeffs must hold an odd number of elements");
+    }
+    let window_size = coeffs.len();
+    let body_size = data.len() - (window_size - 1);
+    let size = fft_size(window_size);
+    let step = size - (window_size - 1);
+    let fft = Fft::new(size);
+    // The correlation with `coeffs` is the convolution with the reversed row, whose transform is
+    // scaled here for the inverse transform
+    let mut kernel = vec![[0.0; 2]; size];
+    kernel
+        .iter_mut()
+        .zip(coeffs.iter().rev())
+        .for_each(|(k, c)| *k = [c / size as f64, 0.0]);
+    fft.forward(&mut kernel);
+
+    let out = &mut buf[window_size / 2..][..body_size];
+    let mut block = vec![[0.0; 2]; size];
+    let mut start = 0;
+    while start < body_size {
+        // The outputs from `start` in the real part, and the next `step` of them in the imaginary
+        for (part, from) in [(0, start), (1, start + step)] {
+            block.iter_mut().enumerate().for_each(|(i, z)| {
+                z[part] = data.get(from + i).map_or(0.0, |x| to_f64(*x));
+            });
+        }
+        fft.forward(&mut block);
+        block.iter_mut().zip(kernel.iter()).for_each(|(z, k)| {
+            *z = [z[0] * k[0] - z[1] * k[1], -(z[0] * k[1] + z[1] * k[0])];
+        });
+        fft.forward(&mut block);
+        // Only the last `step` elements of the circular convolution are free of wrap-around, and
+        // the imaginary part comes out conjugated
+        for (part, from, sign) in [(0, start, 1.0), (1, start + step, -1.0)] {
+            if from >= body_size {
+                break;
+            }
+            out[from..body_size.min(from + step)]
+                .iter_mut()
+                .zip(block[window_size - 1..].iter())
+                .for_each(|(out, z)| *out = from_f64(sign * z[part]));
+        }
+        start += 2 * step;
+    }
+}
+
+/// `sav_gol_dyn` by overlap-save FFT convolution, for rows of hundreds of points.
+///
+/// As with `sav_gol_dyn`, the fringes of `buf` are left untouched.
+/// ```
+///     use staged_sg_filter::coeffs::fit_coeffs;
+///     use staged_sg_filter::fft::sav_gol_fft;
+///     // A quadratic goes through a quadratic fit unchanged
+///     let v: Vec<f64> = (0..1000).map(|i| (i * i) as f64 * 1e-3).collect();
+///     let mut buf = vec![0.0; 1000];
+///     sav_gol_fft(&mut buf, &v, &fit_coeffs(200, 2, 0, 0.0));
+///     assert!((buf[500] - 250.0).abs() < 1e-9);
+///```
+pub fn sav_gol_fft(buf: &mut [f64], data: &[f64], coeffs: &[f64]) {
+    overlap_save(buf, data, coeffs, |x| x, |y| y);
+}
+
+/// `sav_gol_dyn_f32` by overlap-save FFT convolution, transforming in `f64`.
+pub fn sav_gol_fft_f32(buf: &mut [f32], data: &[f32], coeffs: &[f32]) {
+    let coeffs: Vec<f64> = coeffs.iter().map(|&c| c as f64).collect();
+    overlap_save(buf, data, &coeffs, |x| x as f64, |y| y as f32);
+}
+
+#[test]
+fn test_sav_gol_fft() {
+    use crate::coeffs::fit_coeffs;
+    use crate::dot_prod_update;
+    let v = crate::test_util::noisy_sine(3000, 0.013, 5.0, 17, 0.1);
+    // Lengths around the block boundaries, with one or both parts of the last transform used
+    for (half_width, order, deriv, len) in [
+        (40, 3, 0, 3000),
+        (100, 6, 0, 2800),
+        (100, 4, 1, 900),
+        (333, 2, 2, 667),
+        (5, 2, 0, 88),
+    ] {
+        let data = &v[..len];
+        let coeffs = fit_coeffs(half_width, order, deriv, 0.0);
+        let mut ans = vec![f64::NAN; len];
+        ans.iter_mut()
+            .skip(half_width)
+            .zip(data.windows(2 * half_width + 1))
+            .for_each(|(a, w)| dot_prod_update(a, w, &coeffs));
+        let mut buf = vec![f64::NAN; len];
+        sav_gol_fft(&mut buf, data, &coeffs);
+        for (b, a) in buf.iter().zip(ans.iter()) {
+            assert!(b.is_nan() && a.is_nan() || (b - a).abs() < 1e-12, "{b} {a}");
+        }
+    }
+
+    let w: Vec<f32> = v.iter().map(|&x| x as f32).collect();
+    let coeffs: Vec<f32> = fit_coeffs(150, 3, 0, 0.0)
+        .iter()
+        .map(|&c| c as f32)
+        .collect();
+    let mut ans = vec![0.0f32; 3000];
+    crate::sav_gol_direct_f32(&mut ans, &w, &coeffs);
+    let mut buf = vec![0.0f32; 3000];
+    sav_gol_fft_f32(&mut buf, &w, &coeffs);
+    (150..2850).for_each(|i| assert!((buf[i] - ans[i]).abs() < 1e-4));
+}
+
+#[test]
+#[should_panic(expected = "odd number")]
+fn test_sav_gol_fft_even() {
+    sav_gol_fft(&mut [0.0; 10], &[0.0; 10], &[0.5, 0.5]);
+}
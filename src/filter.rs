@@ -0,0 +1,191 @@
+//! Savitzky-Golay filter configured at runtime.
+//!
+//! `SavGolFilter` is for parameters that are only known at runtime, e.g. read from a config
+//! file. Its coefficients are computed once by `new` and reused by every `apply`, which runs on
+//! the `sav_gol_with::<WINDOW>` instantiation for half-widths `1..=25` and by FFT convolution for
+//! wider windows.
+
+use crate::boundary::{fill_fringes_with, fill_fringes_with_f32, fringe_coeffs, Mode};
+use crate::{sav_gol_dyn, sav_gol_dyn_f32};
+
+/// Filter of a `2 * half_width + 1` window, fitting a polynomial of order `order` and writing its
+/// `deriv`-th derivative (per sample), with the fringes handled by `mode`.
+/// ```
+///     use staged_sg_filter::boundary::Mode;
+///     use staged_sg_filter::filter::SavGolFilter;
+///     let filter = SavGolFilter::new(3, 2, 1, Mode::Interp).unwrap();
+///     let v: Vec<f64> = (0..10).map(|i| (i * i) as f64).collect();
+///     let mut buf = vec![0.0; 10];
+///     filter.apply(&mut buf, &v);
+///     assert!(buf.iter().enumerate().all(|(i, d)| (d - 2.0 * i as f64).abs() < 1e-9));
+///```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavGolFilter {
+    half_width: usize,
+    order: usize,
+    deriv: usize,
+    mode: Mode,
+    coeffs: Vec<f64>,
+    coeffs_f32: Vec<f32>,
+    /// Rows evaluating the fit on the fringes, only for `Mode::Interp`
+    interp: Vec<Vec<f64>>,
+}
+
+impl SavGolFilter {
+    /// Checks the parameters and computes the coefficients.
+    pub fn new(half_width: usize, order: usize, deriv: usize, mode: Mode) -> Result<Self, String> {
+        if half_width == 0 {
+            return Err("half_width must be at least 1".to_string());
+        }
+        if order > 2 * half_width {
+            return Err(format!(
+                "order must be at most 2 * half_width = {}",
+                2 * half_width
+            ));
+        }
+        let (coeffs, interp) = fringe_coeffs(half_width, order, deriv, mode);
+        Ok(SavGolFilter {
+            half_width,
+            order,
+            deriv,
+            mode,
+            coeffs_f32: coeffs.iter().map(|&c| c as f32).collect(),
+            coeffs,
+            interp,
+        })
+    }
+
+    pub fn half_width(&self) -> usize {
+        self.half_width
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    pub fn deriv(&self) -> usize {
+        self.deriv
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// The coefficient row applied to every window
+    pub fn coeffs(&self) -> &[f64] {
+        &self.coeffs
+    }
+
+    fn check(&self, buf_len: usize, data_len: usize) {
+        if buf_len != data_len {
+            panic!("buf and data must have the same length");
+        }
+        if data_len < 2 * self.half_width + 1 {
+            panic!("data must hold at least 2 * half_width + 1 elements");
+        }
+    }
+
+    /// Filters `data` into the whole of `buf`, fringes included.
+    ///
+    /// # Panics
+    /// If `buf` and `data` differ in length, or `data` is shorter than the window.
+    pub fn apply(&self, buf: &mut [f64], data: &[f64]) {
+        self.check(buf.len(), data.len());
+        sav_gol_dyn(buf, data, &self.coeffs);
+        fill_fringes_with(buf, data, &self.coeffs, &self.interp, self.mode);
+    }
+
+    /// `f32` version of `apply`, the fringes are computed in `f64`.
+    pub fn apply_f32(&self, buf: &mut [f32], data: &[f32]) {
+        self.check(buf.len(), data.len());
+        sav_gol_dyn_f32(buf, data, &self.coeffs_f32);
+        fill_fringes_with_f32(buf, data, &self.coeffs, &self.interp, self.mode);
+    }
+}
+
+#[test]
+fn test_sav_gol_filter() {
+    use crate::boundary::{fill_fringes, fill_fringes_f32};
+    use crate::coeffs::fit_coeffs;
+    let v: Vec<f64> = (0..80)
+        .map(|i| (i as f64 * 0.3).sin() + (i % 7) as f64)
+        .collect();
+    let w: Vec<f32> = v.iter().map(|&x| x as f32).collect();
+    // Half-widths on the const generic paths and past them
+    for (half_width, order, deriv, mode) in [
+        (1, 2, 0, Mode::Interp),
+        (4, 3, 1, Mode::Mirror),
+        (25, 6, 2, Mode::Wrap),
+        (30, 4, 0, Mode::Interp),
+        (12, 2, 0, Mode::Constant(1.5)),
+    ] {
+        let filter = SavGolFilter::new(half_width, order, deriv, mode).unwrap();
+        let coeffs = fit_coeffs(half_width, order, deriv, 0.0);
+        assert_eq!(filter.coeffs(), coeffs.as_slice());
+
+        let mut ans = vec![0.0; 80];
+        sav_gol_dyn(&mut ans, &v, &coeffs);
+        fill_fringes(&mut ans, &v, half_width, order, deriv, mode);
+        let mut buf = vec![f64::NAN; 80];
+        // Reused across calls
+        for _ in 0..2 {
+            filter.apply(&mut buf, &v);
+            assert_eq!(buf, ans, "{half_width} {order} {deriv} {mode:?}");
+        }
+
+        let coeffs: Vec<f32> = coeffs.iter().map(|&c| c as f32).collect();
+        let mut ans = vec![0.0f32; 80];
+        sav_gol_dyn_f32(&mut ans, &w, &coeffs);
+        fill_fringes_f32(&mut ans, &w, half_width, order, deriv, mode);
+        let mut buf = vec![f32::NAN; 80];
+        filter.apply_f32(&mut buf, &w);
+        assert_eq!(buf, ans);
+    }
+}
+
+#[test]
+fn test_sav_gol_filter_fft() {
+    use crate::boundary::fill_fringes;
+    use crate::coeffs::fit_coeffs;
+    use crate::sav_gol_direct;
+    let v: Vec<f64> = (0..2000)
+        .map(|i| (i as f64 * 0.01).sin() * 3.0 + ((i * 31) % 11) as f64 * 0.2)
+        .collect();
+    for mode in [
+        Mode::Interp,
+        Mode::Mirror,
+        Mode::Nearest,
+        Mode::Constant(-2.0),
+        Mode::Wrap,
+    ] {
+        let filter = SavGolFilter::new(150, 4, 1, mode).unwrap();
+        let mut ans = vec![0.0; 2000];
+        sav_gol_direct(&mut ans, &v, &fit_coeffs(150, 4, 1, 0.0));
+        fill_fringes(&mut ans, &v, 150, 4, 1, mode);
+        let mut buf = vec![f64::NAN; 2000];
+        filter.apply(&mut buf, &v);
+        (0..2000).for_each(|i| assert!((buf[i] - ans[i]).abs() < 1e-12, "{mode:?} {i}"));
+    }
+}
+
+#[test]
+fn test_sav_gol_filter_new() {
+    assert!(SavGolFilter::new(0, 0, 0, Mode::Interp).is_err());
+    assert_eq!(
+        SavGolFilter::new(2, 5, 0, Mode::Interp),
+        Err("order must be at most 2 * half_width = 4".to_string())
+    );
+    let filter = SavGolFilter::new(2, 4, 3, Mode::Nearest).unwrap();
+    assert_eq!(
+        (filter.half_width(), filter.order(), filter.deriv()),
+        (2, 4, 3)
+    );
+    assert_eq!(filter.mode(), Mode::Nearest);
+}
+
+#[test]
+#[should_panic(expected = "data must hold")]
+fn test_sav_gol_filter_short() {
+    let filter = SavGolFilter::new(3, 2, 0, Mode::Interp).unwrap();
+    filter.apply(&mut [0.0; 6], &[0.0; 6]);
+}
@@ -0,0 +1,290 @@
+//! Fixed-point filters for targets without an FPU, e.g. on raw ADC counts.
+//!
+//! The rows of `COEFFS` are rounded at compile time to Q15 (`i16`, scaled by `2^15`) or Q31
+//! (`i32`, scaled by `2^31`) coefficients, so these kernels only use integer arithmetic and
+//! `core`. Q15 accumulates in an `i32`, which is the `i16 x i16` multiply-accumulate of DSP
+//! instruction sets, Q31 in an `i64`. Every output is rounded to nearest (ties up) and saturated
+//! to the range of the sample type.
+//!
+//! Compared to `sav_gol` on the same samples as `f64`, rounded to an integer and clamped to the
+//! sample range, the error is at most:
+//!
+//! * Q15: `WINDOW + 1` counts. Each of the `2 * WINDOW + 1` coefficients is off by at most
+//!   `2^-16`, which on a full-scale input adds up to `(2 * WINDOW + 1) / 2` counts, plus half a
+//!   count of rounding. Away from full scale it shrinks in proportion.
+//! * Q31: 1 count, as the coefficients add at most `(2 * WINDOW + 1) * 2^-17` counts to the
+//!   rounding, so only results right on a rounding tie can end up on the other side.
+//!
+//! The accumulators can't overflow: the absolute values of every row add up to less than 2 (the
+//! widest is `1.85` for `M = 10`), which is checked when the coefficients are computed, so the
+//! sums stay below `2 * 2^15 * 2^15 = 2^31` and `2 * 2^15 * 2^31 = 2^47`.
+//!
+//! `u16` samples, e.g. from a unipolar ADC, are offset by `32768` into the `i16` range. The rows
+//! add up to 1, so this doesn't change the result.
+
+use crate::coeffs::COEFFS;
+
+/// Length of the widest row of `COEFFS`
+const MAX_WINDOW_SIZE: usize = 51;
+
+/// Rounds `row` to nearest with `frac_bits` fractional bits, ties away from zero
+const fn quantize(row: &[f64], frac_bits: u32) -> [i64; MAX_WINDOW_SIZE] {
+    let scale = (1u64 << frac_bits) as f64;
+    let max = (1i64 << frac_bits) - 1;
+    let mut out = [0i64; MAX_WINDOW_SIZE];
+    let mut sum_abs = 0;
+    let mut i = 0;
+    while i < row.len() {
+        let x = row[i] * scale;
+        let q = if x >= 0.0 {
+            (x + 0.5) as i64
+        } else {
+            (x - 0.5) as i64
+        };
+        // Only the identity row (`M = 2 * WINDOW`) reaches 1.0, which doesn't fit
+        out[i] = if q > max { max } else { q };
+        sum_abs += out[i].abs();
+        i += 1;
+    }
+    if sum_abs >= 2 << frac_bits {
+        panic!("the coefficients must add up to less than 2 in absolute value");
+    }
+    out
+}
+
+/// The quantized rows, computed once per `(WINDOW, M)` at compile time
+struct Table<const WINDOW: usize, const M: usize>;
+
+impl<const WINDOW: usize, const M: usize> Table<WINDOW, M> {
+    const Q15: [i16; MAX_WINDOW_SIZE] = {
+        let q = quantize(COEFFS[M - 1][WINDOW - 1], 15);
+        let mut out = [0i16; MAX_WINDOW_SIZE];
+        let mut i = 0;
+        while i < MAX_WINDOW_SIZE {
+            out[i] = q[i] as i16;
+            i += 1;
+        }
+        out
+    };
+    const Q31: [i32; MAX_WINDOW_SIZE] = {
+        let q = quantize(COEFFS[M - 1][WINDOW - 1], 31);
+        let mut out = [0i32; MAX_WINDOW_SIZE];
+        let mut i = 0;
+        while i < MAX_WINDOW_SIZE {
+            out[i] = q[i] as i32;
+            i += 1;
+        }
+        out
+    };
+}
+
+/// Q15 coefficients of `get_coeffs::<WINDOW, M>()`, that is rounded `c * 2^15`.
+pub fn get_coeffs_q15<const WINDOW: usize, const M: usize>() -> &'static [i16] {
+    &Table::<WINDOW, M>::Q15[..2 * WINDOW + 1]
+}
+
+/// Q31 coefficients of `get_coeffs::<WINDOW, M>()`, that is rounded `c * 2^31`.
+pub fn get_coeffs_q31<const WINDOW: usize, const M: usize>() -> &'static [i32] {
+    &Table::<WINDOW, M>::Q31[..2 * WINDOW + 1]
+}
+
+#[inline]
+fn dot_prod_q15(data: impl Iterator<Item = i32>, coeffs: &[i16]) -> i32 {
+    data.zip(coeffs.iter())
+        .fold(1 << 14, |acc, (x, &c)| acc + x * c as i32)
+        >> 15
+}
+
+#[inline]
+fn dot_prod_q31(data: impl Iterator<Item = i64>, coeffs: &[i32]) -> i64 {
+    data.zip(coeffs.iter())
+        .fold(1 << 30, |acc, (x, &c)| acc + x * c as i64)
+        >> 31
+}
+
+/// `sav_gol` on `i16` samples with Q15 coefficients and an `i32` accumulator.
+///
+/// As with `sav_gol`, the fringes of `buf` are left untouched.
+/// ```
+///     use staged_sg_filter::fixed::sav_gol_q15;
+///     let v: Vec<i16> = (0..7).map(|i| if i % 2 == 0 { 0 } else { 3000 }).collect();
+///     let mut buf = vec![0; 7];
+///     sav_gol_q15::<1, 1>(&mut buf, &v);
+///     assert_eq!(buf, [0, 1000, 2000, 1000, 2000, 1000, 0]);
+///```
+pub fn sav_gol_q15<const WINDOW: usize, const M: usize>(buf: &mut [i16], data: &[i16]) {
+    let coeffs = get_coeffs_q15::<WINDOW, M>();
+    let window_size = 2 * WINDOW + 1;
+    let body_size = data.len() - (window_size - 1);
+    buf.iter_mut()
+        .skip(window_size / 2)
+        .zip(data.windows(window_size))
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            let y = dot_prod_q15(data.iter().map(|&x| x as i32), coeffs);
+            *buf = y.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        });
+}
+
+/// `sav_gol_q15` on `u16` samples.
+pub fn sav_gol_q15_u16<const WINDOW: usize, const M: usize>(buf: &mut [u16], data: &[u16]) {
+    let coeffs = get_coeffs_q15::<WINDOW, M>();
+    let window_size = 2 * WINDOW + 1;
+    let body_size = data.len() - (window_size - 1);
+    buf.iter_mut()
+        .skip(window_size / 2)
+        .zip(data.windows(window_size))
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            let y = dot_prod_q15(data.iter().map(|&x| x as i32 - 32768), coeffs) + 32768;
+            *buf = y.clamp(0, u16::MAX as i32) as u16;
+        });
+}
+
+/// `sav_gol` on `i16` samples with Q31 coefficients and an `i64` accumulator.
+pub fn sav_gol_q31<const WINDOW: usize, const M: usize>(buf: &mut [i16], data: &[i16]) {
+    let coeffs = get_coeffs_q31::<WINDOW, M>();
+    let window_size = 2 * WINDOW + 1;
+    let body_size = data.len() - (window_size - 1);
+    buf.iter_mut()
+        .skip(window_size / 2)
+        .zip(data.windows(window_size))
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            let y = dot_prod_q31(data.iter().map(|&x| x as i64), coeffs);
+            *buf = y.clamp(i16::MIN as i64, i16::MAX as i64) as i16;
+        });
+}
+
+/// `sav_gol_q31` on `u16` samples.
+pub fn sav_gol_q31_u16<const WINDOW: usize, const M: usize>(buf: &mut [u16], data: &[u16]) {
+    let coeffs = get_coeffs_q31::<WINDOW, M>();
+    let window_size = 2 * WINDOW + 1;
+    let body_size = data.len() - (window_size - 1);
+    buf.iter_mut()
+        .skip(window_size / 2)
+        .zip(data.windows(window_size))
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            let y = dot_prod_q31(data.iter().map(|&x| x as i64 - 32768), coeffs) + 32768;
+            *buf = y.clamp(0, u16::MAX as i64) as u16;
+        });
+}
+
+/// Random full-scale samples from a fixed seed
+#[cfg(test)]
+fn random_samples(n: usize) -> Vec<i16> {
+    let mut rng = crate::test_util::XorShift::new(0x2545_f491_4f6c_dd1d);
+    (0..n)
+        .map(|_| (rng.next_u64() >> 48) as u16 as i16)
+        .collect()
+}
+
+/// Checks the fixed-point kernels of `(WINDOW, M)` against `sav_gol` on `data`
+#[cfg(test)]
+fn check_fixed<const WINDOW: usize, const M: usize>(data: &[i16]) {
+    use crate::sav_gol;
+    let n = data.len();
+    let v: Vec<f64> = data.iter().map(|&x| x as f64).collect();
+    let mut ans = vec![0.0; n];
+    sav_gol::<WINDOW, M>(&mut ans, &v);
+    let ans: Vec<f64> = ans
+        .iter()
+        .map(|y| y.round().clamp(-32768.0, 32767.0))
+        .collect();
+
+    let body = WINDOW..n - WINDOW;
+    let check = |res: &[f64], bound: f64| {
+        res[body.clone()]
+            .iter()
+            .zip(ans[body.clone()].iter())
+            .for_each(|(r, a)| assert!((r - a).abs() <= bound, "{WINDOW} {M}: {r} != {a}"));
+    };
+    let mut buf = vec![0i16; n];
+    sav_gol_q15::<WINDOW, M>(&mut buf, data);
+    check(
+        &buf.iter().map(|&y| y as f64).collect::<Vec<_>>(),
+        (WINDOW + 1) as f64,
+    );
+    sav_gol_q31::<WINDOW, M>(&mut buf, data);
+    check(&buf.iter().map(|&y| y as f64).collect::<Vec<_>>(), 1.0);
+
+    let unsigned: Vec<u16> = data.iter().map(|&x| (x as i32 + 32768) as u16).collect();
+    let mut buf = vec![0u16; n];
+    sav_gol_q15_u16::<WINDOW, M>(&mut buf, &unsigned);
+    let offset = |buf: &[u16]| -> Vec<f64> { buf.iter().map(|&y| y as f64 - 32768.0).collect() };
+    check(&offset(&buf), (WINDOW + 1) as f64);
+    sav_gol_q31_u16::<WINDOW, M>(&mut buf, &unsigned);
+    check(&offset(&buf), 1.0);
+}
+
+#[test]
+fn test_sav_gol_fixed() {
+    let data = random_samples(400);
+    check_fixed::<1, 1>(&data);
+    check_fixed::<1, 2>(&data);
+    check_fixed::<2, 2>(&data);
+    check_fixed::<3, 4>(&data);
+    check_fixed::<5, 3>(&data);
+    check_fixed::<12, 6>(&data);
+    check_fixed::<20, 8>(&data);
+    check_fixed::<25, 10>(&data);
+    // A smooth signal well inside the range
+    let data: Vec<i16> = (0..400)
+        .map(|i| ((i as f64 * 0.05).sin() * 12000.0) as i16)
+        .collect();
+    check_fixed::<4, 2>(&data);
+    check_fixed::<25, 5>(&data);
+}
+
+#[test]
+fn test_sav_gol_fixed_saturation() {
+    use crate::coeffs::get_coeffs;
+    // Full scale with the signs of the coefficients overshoots by their absolute sum (> 1), which
+    // is clamped instead of wrapping around
+    let signs: Vec<i32> = get_coeffs::<3, 4>()
+        .iter()
+        .map(|c| if *c < 0.0 { -1 } else { 1 })
+        .collect();
+    let data: Vec<i16> = signs.iter().map(|s| (s * 32767) as i16).collect();
+    let mut buf = vec![0i16; 7];
+    sav_gol_q15::<3, 4>(&mut buf, &data);
+    assert_eq!(buf[3], i16::MAX);
+    sav_gol_q31::<3, 4>(&mut buf, &data);
+    assert_eq!(buf[3], i16::MAX);
+    check_fixed::<3, 4>(&data);
+
+    let data: Vec<u16> = signs
+        .iter()
+        .map(|s| if *s < 0 { u16::MAX } else { 0 })
+        .collect();
+    let mut buf = vec![0u16; 7];
+    sav_gol_q15_u16::<3, 4>(&mut buf, &data);
+    assert_eq!(buf[3], 0);
+    sav_gol_q31_u16::<3, 4>(&mut buf, &data);
+    assert_eq!(buf[3], 0);
+}
+
+#[test]
+fn test_get_coeffs_fixed() {
+    use crate::coeffs::get_coeffs;
+    let coeffs = get_coeffs::<3, 2>();
+    let q15 = get_coeffs_q15::<3, 2>();
+    let q31 = get_coeffs_q31::<3, 2>();
+    assert_eq!(q15.len(), 7);
+    coeffs
+        .iter()
+        .zip(q15.iter())
+        .zip(q31.iter())
+        .for_each(|((c, &a), &b)| {
+            assert!((c - a as f64 / 32768.0).abs() <= 0.5 / 32768.0);
+            assert!((c - b as f64 / 2147483648.0).abs() <= 0.5 / 2147483648.0);
+        });
+    // The identity row saturates just under 1.0
+    assert_eq!(get_coeffs_q15::<1, 2>()[1], i16::MAX);
+    // Every row leaves the accumulators their headroom, `quantize` panics otherwise
+    COEFFS.iter().flatten().for_each(|row| {
+        quantize(row, 15);
+        quantize(row, 31);
+    });
+}
@@ -0,0 +1,217 @@
+//! Kernels folding the window about its centre to halve the multiplies.
+//!
+//! Smoothing rows are symmetric, `c_{-k} = c_k`, so `sav_gol_folded` adds the two samples `k`
+//! away from the centre before multiplying: `c_0 x_0 + sum_k c_k (x_{-k} + x_k)`. Derivative rows
+//! of odd order are antisymmetric, `c_{-k} = -c_k`, and fold into differences instead. The rows
+//! of `COEFFS` are all smoothing rows, so `sav_gol_folded` folds them as symmetric; runtime rows
+//! from `coeffs::fit_coeffs` go through `sav_gol_with_folded` with their `Symmetry`.
+//!
+//! The sums are rounded in a different order than `dot_prod_update`'s, so the output differs
+//! from `sav_gol` in the last few bits. Narrow windows are bound by memory either way, the fold
+//! pays off on wide ones: about 2x faster at `WINDOW = 25` by the `savgol_window`/`savgol_folded`
+//! benchmarks.
+
+use crate::coeffs::get_coeffs;
+use crate::coeffs_f32::get_coeffs_f32;
+use crate::{mul_add_f32, mul_add_f64};
+
+/// Parity of a coefficient row about its centre.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Symmetry {
+    /// `c_{-k} = c_k`, as for smoothing and even derivative rows
+    Symmetric,
+    /// `c_{-k} = -c_k` (and `c_0 = 0`), as for odd derivative rows
+    Antisymmetric,
+}
+
+/// `dot_prod_update` on the folded window, reading only the left half and the centre of `coeffs`
+#[inline]
+pub fn dot_prod_folded(buf: &mut f64, data: &[f64], coeffs: &[f64], symmetry: Symmetry) {
+    let half = coeffs.len() / 2;
+    let (left, right) = data.split_at(half);
+    let folded = left.iter().zip(right[1..].iter().rev());
+    *buf = match symmetry {
+        Symmetry::Symmetric => folded
+            .zip(coeffs.iter())
+            .fold(coeffs[half] * right[0], |acc, ((a, b), c)| {
+                mul_add_f64(*a + *b, *c, acc)
+            }),
+        Symmetry::Antisymmetric => folded
+            .zip(coeffs.iter())
+            .fold(0.0, |acc, ((a, b), c)| mul_add_f64(*a - *b, *c, acc)),
+    };
+}
+
+#[inline]
+pub fn dot_prod_folded_f32(buf: &mut f32, data: &[f32], coeffs: &[f32], symmetry: Symmetry) {
+    let half = coeffs.len() / 2;
+    let (left, right) = data.split_at(half);
+    let folded = left.iter().zip(right[1..].iter().rev());
+    *buf = match symmetry {
+        Symmetry::Symmetric => folded
+            .zip(coeffs.iter())
+            .fold(coeffs[half] * right[0], |acc, ((a, b), c)| {
+                mul_add_f32(*a + *b, *c, acc)
+            }),
+        Symmetry::Antisymmetric => folded
+            .zip(coeffs.iter())
+            .fold(0.0, |acc, ((a, b), c)| mul_add_f32(*a - *b, *c, acc)),
+    };
+}
+
+/// `sav_gol` with the window folded about its centre, `WINDOW + 1` multiplies per output.
+///
+/// As with `sav_gol`, the fringes of `buf` are left untouched.
+/// ```
+///     use staged_sg_filter::folded::sav_gol_folded;
+///     let v = vec![0.0, 3.0, 0.0, 3.0, 0.0];
+///     let mut buf = vec![0.0; 5];
+///     sav_gol_folded::<1, 1>(&mut buf, &v);
+///     assert!((buf[2] - 2.0).abs() < 1e-12);
+///```
+pub fn sav_gol_folded<const WINDOW: usize, const M: usize>(buf: &mut [f64], data: &[f64]) {
+    sav_gol_with_folded::<WINDOW>(buf, data, get_coeffs::<WINDOW, M>(), Symmetry::Symmetric);
+}
+
+pub fn sav_gol_folded_f32<const WINDOW: usize, const M: usize>(buf: &mut [f32], data: &[f32]) {
+    sav_gol_with_folded_f32::<WINDOW>(
+        buf,
+        data,
+        get_coeffs_f32::<WINDOW, M>(),
+        Symmetry::Symmetric,
+    );
+}
+
+/// `sav_gol_with` with the window folded about its centre, for a row of the given `symmetry`.
+///
+/// Only the left half and the centre of `coeffs` are read, the right half is taken to mirror
+/// them.
+/// ```
+///     use staged_sg_filter::folded::{sav_gol_with_folded, Symmetry};
+///     let v = [0.0, 1.0, 4.0, 9.0, 16.0, 25.0, 36.0, 49.0, 64.0];
+///     let mut buf = [0.0; 9];
+///     // The slope of `i^2` is `2 i`, with the slope row of a quadratic fit over 5 samples
+///     let slope = [-0.2, -0.1, 0.0, 0.1, 0.2];
+///     sav_gol_with_folded::<2>(&mut buf, &v, &slope, Symmetry::Antisymmetric);
+///     assert!((buf[4] - 8.0).abs() < 1e-12);
+///```
+pub fn sav_gol_with_folded<const WINDOW: usize>(
+    buf: &mut [f64],
+    data: &[f64],
+    coeffs: &[f64],
+    symmetry: Symmetry,
+) {
+    let window_size = 2 * WINDOW + 1;
+    // Pin the length of `coeffs` so the trip count stays known at compile time
+    let coeffs = &coeffs[..window_size];
+    let body_size = data.len() - (window_size - 1);
+    buf.iter_mut()
+        // Start the iteration without the `windows` reaching before `buf` starts
+        .skip(window_size / 2)
+        .zip(data.windows(window_size))
+        // Advance `body_size` iterations so that `windows` doesn't go over the end of `buf`
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            dot_prod_folded(buf, data, coeffs, symmetry);
+        });
+}
+
+pub fn sav_gol_with_folded_f32<const WINDOW: usize>(
+    buf: &mut [f32],
+    data: &[f32],
+    coeffs: &[f32],
+    symmetry: Symmetry,
+) {
+    let window_size = 2 * WINDOW + 1;
+    // Pin the length of `coeffs` so the trip count stays known at compile time
+    let coeffs = &coeffs[..window_size];
+    let body_size = data.len() - (window_size - 1);
+    buf.iter_mut()
+        // Start the iteration without the `windows` reaching before `buf` starts
+        .skip(window_size / 2)
+        .zip(data.windows(window_size))
+        // Advance `body_size` iterations so that `windows` doesn't go over the end of `buf`
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            dot_prod_folded_f32(buf, data, coeffs, symmetry);
+        });
+}
+
+#[test]
+fn test_dot_prod_folded() {
+    let data = [1.0, 2.0, 4.0, 8.0, 16.0];
+    let mut buf = 0.0;
+    dot_prod_folded(
+        &mut buf,
+        &data,
+        &[1.0, 0.5, 0.25, 0.5, 1.0],
+        Symmetry::Symmetric,
+    );
+    assert_eq!(buf, 17.0 + 5.0 + 1.0);
+    dot_prod_folded(
+        &mut buf,
+        &data,
+        &[1.0, 0.5, 0.0, -0.5, -1.0],
+        Symmetry::Antisymmetric,
+    );
+    assert_eq!(buf, -15.0 - 3.0);
+    let data = data.map(|x| x as f32);
+    let mut buf = 0.0f32;
+    dot_prod_folded_f32(
+        &mut buf,
+        &data,
+        &[2.0, 1.0, 3.0, 1.0, 2.0],
+        Symmetry::Symmetric,
+    );
+    assert_eq!(buf, 34.0 + 10.0 + 12.0);
+}
+
+#[test]
+fn test_sav_gol_folded() {
+    use crate::{sav_gol, sav_gol_f32};
+    let v = crate::test_util::noisy_sine(200, 0.11, 3.0, 23, 0.1);
+    let mut ans = vec![f64::NAN; 200];
+    sav_gol::<12, 4>(&mut ans, &v);
+    let mut buf = vec![f64::NAN; 200];
+    sav_gol_folded::<12, 4>(&mut buf, &v);
+    assert!(buf[..12].iter().chain(&buf[188..]).all(|b| b.is_nan()));
+    (12..188).for_each(|i| assert!((buf[i] - ans[i]).abs() < 1e-12));
+
+    let w: Vec<f32> = v.iter().map(|&x| x as f32).collect();
+    let mut ans = vec![0.0f32; 200];
+    sav_gol_f32::<25, 2>(&mut ans, &w);
+    let mut buf = vec![0.0f32; 200];
+    sav_gol_folded_f32::<25, 2>(&mut buf, &w);
+    (25..175).for_each(|i| assert!((buf[i] - ans[i]).abs() < 1e-5));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_sav_gol_with_folded() {
+    use crate::coeffs::fit_coeffs;
+    use crate::sav_gol_with;
+    let v: Vec<f64> = (0..100)
+        .map(|i| (i as f64 * 0.2).cos() + (i % 5) as f64)
+        .collect();
+    for (deriv, symmetry) in [
+        (0, Symmetry::Symmetric),
+        (1, Symmetry::Antisymmetric),
+        (2, Symmetry::Symmetric),
+        (3, Symmetry::Antisymmetric),
+    ] {
+        let coeffs = fit_coeffs(7, 4, deriv, 0.0);
+        let mut ans = vec![0.0; 100];
+        sav_gol_with::<7>(&mut ans, &v, &coeffs);
+        let mut buf = vec![0.0; 100];
+        sav_gol_with_folded::<7>(&mut buf, &v, &coeffs, symmetry);
+        (7..93).for_each(|i| assert!((buf[i] - ans[i]).abs() < 1e-12, "{deriv}"));
+
+        let coeffs: Vec<f32> = coeffs.iter().map(|&c| c as f32).collect();
+        let w: Vec<f32> = v.iter().map(|&x| x as f32).collect();
+        let mut ans = vec![0.0f32; 100];
+        crate::sav_gol_with_f32::<7>(&mut ans, &w, &coeffs);
+        let mut buf = vec![0.0f32; 100];
+        sav_gol_with_folded_f32::<7>(&mut buf, &w, &coeffs, symmetry);
+        (7..93).for_each(|i| assert!((buf[i] - ans[i]).abs() < 1e-4, "{deriv}"));
+    }
+}
@@ -0,0 +1,88 @@
+//! Half-precision (`f16` and `bf16`) filters, behind the `half` feature.
+//!
+//! Samples are stored in 16 bits and widened to `f32` inside the dot product, which accumulates
+//! in `f32` with the `COEFFS_F32` rows like `sav_gol_f32`. Only the output is rounded back to half
+//! precision, so the result is `sav_gol_f32` on the widened samples, rounded once, for half the
+//! memory traffic. The samples are widened a block at a time into a buffer on the stack, so that
+//! each is converted once rather than once per window it is in.
+
+use crate::coeffs_f32::get_coeffs_f32;
+use crate::dot_prod_update_f32;
+pub use ::half::{bf16, f16};
+
+/// Outputs computed per block, the samples of a block are widened to `f32` once
+const BLOCK: usize = 256;
+
+/// `sav_gol_f32` on samples that widen to `f32` with `to_f32`, rounded back with `from_f32`
+fn sav_gol_blocks<T: Copy, const WINDOW: usize, const M: usize>(
+    buf: &mut [T],
+    data: &[T],
+    to_f32: impl Fn(T) -> f32,
+    from_f32: impl Fn(f32) -> T,
+) {
+    let coeffs = get_coeffs_f32::<WINDOW, M>();
+    let window_size = 2 * WINDOW + 1;
+    let body_size = data.len() - (window_size - 1);
+    // Sized for the widest window of `COEFFS_F32`
+    let mut wide = [0.0f32; BLOCK + 50];
+    let mut out = [0.0f32; BLOCK];
+    let mut start = 0;
+    while start < body_size {
+        let len = BLOCK.min(body_size - start);
+        let wide = &mut wide[..len + window_size - 1];
+        wide.iter_mut()
+            .zip(data[start..].iter())
+            .for_each(|(w, x)| *w = to_f32(*x));
+        out.iter_mut()
+            .zip(wide.windows(window_size))
+            .for_each(|(out, data)| dot_prod_update_f32(out, data, coeffs));
+        buf[start + WINDOW..start + WINDOW + len]
+            .iter_mut()
+            .zip(out.iter())
+            .for_each(|(buf, out)| *buf = from_f32(*out));
+        start += len;
+    }
+}
+
+/// `sav_gol_f32` on `f16` samples.
+///
+/// As with `sav_gol`, the fringes of `buf` are left untouched.
+/// ```
+///     use staged_sg_filter::half::{f16, sav_gol_f16};
+///     let v: Vec<f16> = [0.0, 3.0, 0.0, 3.0, 0.0].map(f16::from_f32).to_vec();
+///     let mut buf = vec![f16::ZERO; 5];
+///     sav_gol_f16::<1, 1>(&mut buf, &v);
+///     assert_eq!(buf[2], f16::from_f32(2.0));
+///```
+pub fn sav_gol_f16<const WINDOW: usize, const M: usize>(buf: &mut [f16], data: &[f16]) {
+    sav_gol_blocks::<f16, WINDOW, M>(buf, data, f16::to_f32, f16::from_f32);
+}
+
+/// `sav_gol_f32` on `bf16` samples.
+pub fn sav_gol_bf16<const WINDOW: usize, const M: usize>(buf: &mut [bf16], data: &[bf16]) {
+    sav_gol_blocks::<bf16, WINDOW, M>(buf, data, bf16::to_f32, bf16::from_f32);
+}
+
+#[test]
+fn test_sav_gol_half() {
+    use crate::sav_gol_f32;
+    let v: Vec<f32> = (0..300)
+        .map(|i| (i as f32 * 0.07).sin() * 40.0 + (i % 5) as f32)
+        .collect();
+
+    let data: Vec<f16> = v.iter().map(|&x| f16::from_f32(x)).collect();
+    let wide: Vec<f32> = data.iter().map(|x| x.to_f32()).collect();
+    let mut ans = vec![0.0f32; 300];
+    sav_gol_f32::<5, 3>(&mut ans, &wide);
+    let mut buf = vec![f16::NAN; 300];
+    sav_gol_f16::<5, 3>(&mut buf, &data);
+    (5..295).for_each(|i| assert_eq!(buf[i], f16::from_f32(ans[i])));
+    assert!(buf[..5].iter().all(|y| y.is_nan()));
+
+    let data: Vec<bf16> = v.iter().map(|&x| bf16::from_f32(x)).collect();
+    let wide: Vec<f32> = data.iter().map(|x| x.to_f32()).collect();
+    sav_gol_f32::<12, 2>(&mut ans, &wide);
+    let mut buf = vec![bf16::NAN; 300];
+    sav_gol_bf16::<12, 2>(&mut buf, &data);
+    (12..288).for_each(|i| assert_eq!(buf[i], bf16::from_f32(ans[i])));
+}
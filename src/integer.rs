@@ -0,0 +1,121 @@
+//! Filters on integer samples with floating point output.
+//!
+//! `sav_gol_int` reads `u16`, `i32` and the other integer types straight from the slice and
+//! converts each sample inside the dot product, so a recording never has to be widened into a
+//! `Vec<f64>` first. The dot products are the same as `sav_gol`'s, so the output is bit for bit
+//! what `sav_gol` gives on the converted samples.
+
+use crate::coeffs::get_coeffs;
+use crate::coeffs_f32::get_coeffs_f32;
+use crate::{mul_add_f32, mul_add_f64};
+
+/// Integer sample type accepted by `sav_gol_int` and `sav_gol_int_f32`.
+pub trait Sample: Copy {
+    fn to_f64(self) -> f64;
+    /// Rounds to the nearest `f32` past `2^24` in absolute value
+    fn to_f32(self) -> f32;
+}
+
+macro_rules! impl_sample {
+    ($($t:ty)*) => {
+        $(impl Sample for $t {
+            #[inline(always)]
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+            #[inline(always)]
+            fn to_f32(self) -> f32 {
+                self as f32
+            }
+        })*
+    };
+}
+
+impl_sample!(u8 i8 u16 i16 u32 i32);
+
+/// `sav_gol` on integer samples, writing `f64`.
+///
+/// As with `sav_gol`, the fringes of `buf` are left untouched.
+/// ```
+///     use staged_sg_filter::integer::sav_gol_int;
+///     let v: Vec<u16> = vec![0, 3, 0, 3, 0, 3, 0];
+///     let mut buf = vec![0.0; 7];
+///     sav_gol_int::<1, 1, _>(&mut buf, &v);
+///     [0.0, 1.0, 2.0, 1.0, 2.0, 1.0, 0.0]
+///         .iter()
+///         .zip(buf.iter())
+///         .for_each(|(e, b)| assert!((b - e).abs() < 1e-12));
+///```
+pub fn sav_gol_int<const WINDOW: usize, const M: usize, T: Sample>(buf: &mut [f64], data: &[T]) {
+    let coeffs = get_coeffs::<WINDOW, M>();
+    let window_size = 2 * WINDOW + 1;
+    let body_size = data.len() - (window_size - 1);
+    buf.iter_mut()
+        // Start the iteration without the `windows` reaching before `buf` starts
+        .skip(window_size / 2)
+        .zip(data.windows(window_size))
+        // Advance `body_size` iterations so that `windows` doesn't go over the end of `buf`
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            *buf = data
+                .iter()
+                .zip(coeffs.iter())
+                .fold(0.0f64, |acc, (x, c)| mul_add_f64(x.to_f64(), *c, acc));
+        });
+}
+
+/// `sav_gol_f32` on integer samples, writing `f32`.
+pub fn sav_gol_int_f32<const WINDOW: usize, const M: usize, T: Sample>(
+    buf: &mut [f32],
+    data: &[T],
+) {
+    let coeffs = get_coeffs_f32::<WINDOW, M>();
+    let window_size = 2 * WINDOW + 1;
+    let body_size = data.len() - (window_size - 1);
+    buf.iter_mut()
+        // Start the iteration without the `windows` reaching before `buf` starts
+        .skip(window_size / 2)
+        .zip(data.windows(window_size))
+        // Advance `body_size` iterations so that `windows` doesn't go over the end of `buf`
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            *buf = data
+                .iter()
+                .zip(coeffs.iter())
+                .fold(0.0f32, |acc, (x, c)| mul_add_f32(x.to_f32(), *c, acc));
+        });
+}
+
+#[test]
+fn test_sav_gol_int() {
+    use crate::{sav_gol, sav_gol_f32};
+    let mut rng = crate::test_util::XorShift::new(0x9e37_79b9_7f4a_7c15);
+    let unsigned: Vec<u16> = (0..200).map(|_| (rng.next_u64() >> 48) as u16).collect();
+    let signed: Vec<i32> = (0..200)
+        .map(|_| (rng.next_u64() >> 32) as u32 as i32)
+        .collect();
+
+    let v: Vec<f64> = unsigned.iter().map(|&x| x as f64).collect();
+    let mut ans = vec![-1.0; 200];
+    sav_gol::<4, 3>(&mut ans, &v);
+    let mut buf = vec![-1.0; 200];
+    sav_gol_int::<4, 3, _>(&mut buf, &unsigned);
+    assert_eq!(buf, ans);
+
+    let v: Vec<f64> = signed.iter().map(|&x| x as f64).collect();
+    sav_gol::<12, 2>(&mut ans, &v);
+    sav_gol_int::<12, 2, _>(&mut buf, &signed);
+    assert_eq!(buf, ans);
+
+    let v: Vec<f32> = unsigned.iter().map(|&x| x as f32).collect();
+    let mut ans = vec![-1.0f32; 200];
+    sav_gol_f32::<2, 2>(&mut ans, &v);
+    let mut buf = vec![-1.0f32; 200];
+    sav_gol_int_f32::<2, 2, _>(&mut buf, &unsigned);
+    assert_eq!(buf, ans);
+
+    let v: Vec<f32> = signed.iter().map(|&x| x as f32).collect();
+    sav_gol_f32::<7, 4>(&mut ans, &v);
+    sav_gol_int_f32::<7, 4, _>(&mut buf, &signed);
+    assert_eq!(buf, ans);
+}
@@ -0,0 +1,339 @@
+//! Lazy smoothing of iterators.
+//!
+//! `SavGolExt` adds `.sav_gol::<WINDOW, M>()` to every `Iterator<Item = f64>`, yielding the
+//! smoothed signal as the input is pulled, so it never has to be collected into a `Vec`. Only
+//! the last `2 * WINDOW + 1` samples are kept, in a ring buffer allocated once.
+
+use crate::boundary::{fill_fringes_with, fringe_coeffs, Mode};
+use crate::coeffs::{fit_coeffs_at, get_coeffs};
+use crate::dot_prod_update;
+
+/// What the iterator yields for the `WINDOW` samples on either end, whose windows don't fit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fringes {
+    /// Yield the input unfiltered, as `sav_gol` on a copy of the input would leave them
+    Raw,
+    /// Don't yield them, so the output is `2 * WINDOW` samples shorter than the input
+    Skip,
+    /// Filter them like `boundary::fill_fringes`. `Mode::Wrap` is not supported, since the start
+    /// of the signal would depend on its end.
+    ///
+    /// A signal shorter than a window has no body to pad, so whatever the mode, each of its
+    /// samples is replaced by the least-squares fit of order `min(M, len - 1)` over all of them.
+    Filter(Mode),
+}
+
+/// Iterator returned by `SavGolExt::sav_gol`.
+#[derive(Debug, Clone)]
+pub struct SavGol<I, const WINDOW: usize, const M: usize> {
+    iter: I,
+    fringes: Fringes,
+    /// Every sample is written at `pos` and `pos + window_size`, so the current window is always
+    /// the contiguous `ring[pos..pos + window_size]`
+    ring: Vec<f64>,
+    pos: usize,
+    read: usize,
+    /// Fringe values waiting to be yielded
+    queue: Vec<f64>,
+    queued: usize,
+    done: bool,
+}
+
+pub trait SavGolExt: Iterator<Item = f64> + Sized {
+    /// Smooths the iterator with the coefficients of `sav_gol::<WINDOW, M>`, filtering the
+    /// fringes with `Mode::Interp` unless told otherwise with `SavGol::fringes`.
+    /// ```
+    ///     use staged_sg_filter::iter::{Fringes, SavGolExt};
+    ///     let smooth: Vec<f64> = (0..20)
+    ///         .map(|i| (i * i) as f64)
+    ///         .sav_gol::<2, 2>()
+    ///         .collect();
+    ///     assert!((smooth[0] - 0.0).abs() < 1e-9);
+    ///     assert!((smooth[10] - 100.0).abs() < 1e-9);
+    ///     let body = (0..20).map(|i| i as f64).sav_gol::<2, 2>().fringes(Fringes::Skip);
+    ///     assert_eq!(body.count(), 16);
+    ///```
+    fn sav_gol<const WINDOW: usize, const M: usize>(self) -> SavGol<Self, WINDOW, M> {
+        SavGol {
+            iter: self,
+            fringes: Fringes::Filter(Mode::Interp),
+            ring: vec![0.0; 2 * (2 * WINDOW + 1)],
+            pos: 0,
+            read: 0,
+            queue: Vec::with_capacity(2 * WINDOW + 1),
+            queued: 0,
+            done: false,
+        }
+    }
+}
+
+impl<I: Iterator<Item = f64>> SavGolExt for I {}
+
+impl<I: Iterator<Item = f64>, const WINDOW: usize, const M: usize> SavGol<I, WINDOW, M> {
+    /// Sets how the fringes are handled, before iterating.
+    pub fn fringes(mut self, fringes: Fringes) -> Self {
+        if fringes == Fringes::Filter(Mode::Wrap) {
+            panic!("Mode::Wrap can't be used on an iterator");
+        }
+        if self.read > 0 {
+            panic!("fringes must be set before iterating");
+        }
+        self.fringes = fringes;
+        self
+    }
+
+    fn window(&self) -> &[f64] {
+        &self.ring[self.pos..self.pos + 2 * WINDOW + 1]
+    }
+
+    fn push(&mut self, x: f64) {
+        let window_size = 2 * WINDOW + 1;
+        self.ring[self.pos] = x;
+        self.ring[self.pos + window_size] = x;
+        self.pos = (self.pos + 1) % window_size;
+        self.read += 1;
+    }
+
+    fn smooth(&self) -> f64 {
+        let mut buf = 0.0;
+        dot_prod_update(&mut buf, self.window(), get_coeffs::<WINDOW, M>());
+        buf
+    }
+
+    /// Queues the left (`left`) or right fringe of the current window
+    fn queue_fringe(&mut self, left: bool) {
+        let fringe = fringe_values::<WINDOW, M>(self.fringes, self.window(), left);
+        self.queue.extend(fringe);
+    }
+
+    fn pop_queue(&mut self) -> Option<f64> {
+        let x = self.queue.get(self.queued).copied();
+        self.queued += 1;
+        if self.queued >= self.queue.len() {
+            self.queue.clear();
+            self.queued = 0;
+        }
+        x
+    }
+}
+
+/// What `fringes` yields for the left (`left`) or right fringe of `window`, the first or last
+/// `2 * WINDOW + 1` samples of the signal
+pub(crate) fn fringe_values<const WINDOW: usize, const M: usize>(
+    fringes: Fringes,
+    window: &[f64],
+    left: bool,
+) -> Vec<f64> {
+    let range = match left {
+        true => 0..WINDOW,
+        false => WINDOW + 1..2 * WINDOW + 1,
+    };
+    match fringes {
+        Fringes::Skip => vec![],
+        Fringes::Raw => window[range].to_vec(),
+        Fringes::Filter(mode) => {
+            let (coeffs, interp) = fringe_coeffs(WINDOW, M, 0, mode);
+            let mut buf = vec![0.0; window.len()];
+            fill_fringes_with(&mut buf, window, &coeffs, &interp, mode);
+            buf[range].to_vec()
+        }
+    }
+}
+
+/// What `fringes` yields for a whole signal shorter than a window
+pub(crate) fn short_values<const M: usize>(fringes: Fringes, samples: &[f64]) -> Vec<f64> {
+    match fringes {
+        Fringes::Skip => vec![],
+        Fringes::Raw => samples.to_vec(),
+        Fringes::Filter(_) => {
+            let n = samples.len();
+            let t: Vec<f64> = (0..n).map(|k| k as f64 - (n as f64 - 1.0) / 2.0).collect();
+            let order = M.min(n.saturating_sub(1));
+            t.iter()
+                .map(|&x| {
+                    let mut buf = 0.0;
+                    dot_prod_update(&mut buf, samples, &fit_coeffs_at(&t, order, 0, x));
+                    buf
+                })
+                .collect()
+        }
+    }
+}
+
+impl<I: Iterator<Item = f64>, const WINDOW: usize, const M: usize> Iterator
+    for SavGol<I, WINDOW, M>
+{
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        if !self.queue.is_empty() {
+            return self.pop_queue();
+        }
+        if self.done {
+            return None;
+        }
+        let window_size = 2 * WINDOW + 1;
+        if self.read == 0 {
+            // Fill the first window before yielding anything
+            while self.read < window_size {
+                match self.iter.next() {
+                    Some(x) => self.push(x),
+                    None => break,
+                }
+            }
+            if self.read < window_size {
+                self.done = true;
+                let short = short_values::<M>(self.fringes, &self.ring[..self.read]);
+                self.queue.extend(short);
+                return self.pop_queue();
+            }
+            self.queue_fringe(true);
+            self.queue.push(self.smooth());
+            return self.pop_queue();
+        }
+        match self.iter.next() {
+            Some(x) => {
+                self.push(x);
+                Some(self.smooth())
+            }
+            None => {
+                self.done = true;
+                self.queue_fringe(false);
+                self.pop_queue()
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lo, hi) = self.iter.size_hint();
+        // Samples read but not yielded yet
+        let pending = match (self.read, self.done) {
+            (0, _) => 0,
+            (_, false) => WINDOW,
+            (_, true) => 0,
+        } + self.queue.len()
+            - self.queued;
+        let skipped = match (self.fringes, self.read) {
+            (Fringes::Skip, 0) => 2 * WINDOW,
+            (Fringes::Skip, _) if !self.done => WINDOW,
+            _ => 0,
+        };
+        (
+            (lo + pending).saturating_sub(skipped),
+            hi.map(|hi| (hi + pending).saturating_sub(skipped)),
+        )
+    }
+}
+
+#[test]
+fn test_sav_gol_iter() {
+    use crate::boundary::fill_fringes;
+    use crate::sav_gol;
+    let v: Vec<f64> = (0..50)
+        .map(|i| (i as f64 * 0.4).sin() * 3.0 + (i % 3) as f64)
+        .collect();
+    let mut ans = v.clone();
+    sav_gol::<3, 2>(&mut ans, &v);
+
+    // The body matches `sav_gol` exactly
+    let raw: Vec<f64> = v
+        .iter()
+        .copied()
+        .sav_gol::<3, 2>()
+        .fringes(Fringes::Raw)
+        .collect();
+    assert_eq!(raw, ans);
+    let skip: Vec<f64> = v
+        .iter()
+        .copied()
+        .sav_gol::<3, 2>()
+        .fringes(Fringes::Skip)
+        .collect();
+    assert_eq!(skip, ans[3..47]);
+
+    for mode in [
+        Mode::Interp,
+        Mode::Mirror,
+        Mode::Nearest,
+        Mode::Constant(2.0),
+    ] {
+        let mut ans = ans.clone();
+        fill_fringes(&mut ans, &v, 3, 2, 0, mode);
+        let res: Vec<f64> = v
+            .iter()
+            .copied()
+            .sav_gol::<3, 2>()
+            .fringes(Fringes::Filter(mode))
+            .collect();
+        assert_eq!(res.len(), 50);
+        res.iter()
+            .zip(ans.iter())
+            .for_each(|(r, a)| assert!((r - a).abs() < 1e-12, "{mode:?}"));
+    }
+}
+
+#[test]
+fn test_sav_gol_iter_short() {
+    let short = || (0..4).map(|i| i as f64);
+    assert_eq!(
+        short()
+            .sav_gol::<2, 2>()
+            .fringes(Fringes::Raw)
+            .collect::<Vec<_>>(),
+        vec![0.0, 1.0, 2.0, 3.0]
+    );
+    assert_eq!(short().sav_gol::<2, 2>().fringes(Fringes::Skip).count(), 0);
+    // Exactly one window
+    assert_eq!((0..5).map(|i| i as f64).sav_gol::<2, 2>().count(), 5);
+
+    // Filtered, the samples are fitted as a whole: a quadratic goes through unchanged, and a
+    // line fits 4 samples of a parabola
+    let parabola = || (0..4).map(|i| (i * i) as f64);
+    for mode in [Mode::Interp, Mode::Mirror] {
+        let res: Vec<f64> = parabola()
+            .sav_gol::<3, 2>()
+            .fringes(Fringes::Filter(mode))
+            .collect();
+        assert_eq!(res.len(), 4);
+        res.iter()
+            .zip(parabola())
+            .for_each(|(r, a)| assert!((r - a).abs() < 1e-12));
+    }
+    let line: Vec<f64> = parabola().sav_gol::<3, 1>().collect();
+    [-1.0, 2.0, 5.0, 8.0]
+        .iter()
+        .zip(line.iter())
+        .for_each(|(a, r)| assert!((r - a).abs() < 1e-12));
+    assert_eq!(
+        std::iter::once(7.0).sav_gol::<2, 2>().collect::<Vec<_>>(),
+        vec![7.0]
+    );
+    assert_eq!(std::iter::empty().sav_gol::<2, 2>().count(), 0);
+}
+
+#[test]
+fn test_sav_gol_iter_size_hint() {
+    let mut it = (0..30).map(|i| i as f64).sav_gol::<4, 2>();
+    assert_eq!(it.size_hint(), (30, Some(30)));
+    for remaining in (0..30).rev() {
+        it.next().unwrap();
+        assert_eq!(it.size_hint(), (remaining, Some(remaining)));
+    }
+    assert_eq!(it.next(), None);
+    let mut it = (0..30)
+        .map(|i| i as f64)
+        .sav_gol::<4, 2>()
+        .fringes(Fringes::Skip);
+    assert_eq!(it.size_hint(), (22, Some(22)));
+    it.next();
+    assert_eq!(it.size_hint(), (21, Some(21)));
+}
+
+#[test]
+#[should_panic(expected = "Mode::Wrap")]
+fn test_sav_gol_iter_wrap() {
+    let _ = (0..10)
+        .map(|i| i as f64)
+        .sav_gol::<1, 1>()
+        .fringes(Fringes::Filter(Mode::Wrap));
+}
@@ -1,41 +1,98 @@
 //#![feature(array_chunks)]
 //#![feature(portable_simd)]
+// Without `std` only `core` is used: the const generic kernels, `sav_gol_dyn` and the
+// coefficient tables are available, everything that allocates or does I/O is not.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
 use coeffs::get_coeffs;
+#[cfg(feature = "std")]
+use coeffs::{get_deriv_coeffs, get_offset_coeffs, get_predict_coeffs};
 use coeffs_f32::get_coeffs_f32;
 
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod boundary;
+#[allow(clippy::excessive_precision)]
 pub mod coeffs;
+#[allow(clippy::excessive_precision)]
 pub mod coeffs_f32;
+pub mod compensated;
+pub mod complex;
+#[cfg(feature = "std")]
+pub mod crossval;
+#[cfg(feature = "std")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod fft;
+#[cfg(feature = "std")]
+pub mod filter;
+pub mod fixed;
+pub mod folded;
+#[cfg(feature = "half")]
+pub mod half;
+pub mod integer;
+#[cfg(feature = "std")]
+pub mod iter;
+#[cfg(feature = "std")]
+pub mod npy;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "std")]
+pub mod raw;
+#[cfg(feature = "std")]
+pub mod scipy;
+#[cfg(feature = "std")]
+pub mod sliding;
+#[cfg(feature = "stream")]
+pub mod stream;
+#[cfg(test)]
+mod test_util;
+#[cfg(feature = "std")]
+pub mod wav;
+
+/// `a * b + c`, fused when the hardware has FMA (e.g. with `-C target-cpu=native`) or through
+/// `libm` without `std`. Otherwise `mul_add` would be a slow software call, so it stays a plain
+/// multiply and add.
+#[inline(always)]
+pub(crate) fn mul_add_f64(a: f64, b: f64, c: f64) -> f64 {
+    #[cfg(all(feature = "std", any(target_feature = "fma", target_arch = "aarch64")))]
+    return a.mul_add(b, c);
+    #[cfg(all(not(feature = "std"), feature = "libm"))]
+    return libm::fma(a, b, c);
+    #[allow(unreachable_code)]
+    {
+        a * b + c
+    }
+}
+
+#[inline(always)]
+pub(crate) fn mul_add_f32(a: f32, b: f32, c: f32) -> f32 {
+    #[cfg(all(feature = "std", any(target_feature = "fma", target_arch = "aarch64")))]
+    return a.mul_add(b, c);
+    #[cfg(all(not(feature = "std"), feature = "libm"))]
+    return libm::fmaf(a, b, c);
+    #[allow(unreachable_code)]
+    {
+        a * b + c
+    }
+}
 
 /// Small utility function to clean up the `sav_gol` filter
 #[inline]
 pub fn dot_prod_update(buf: &mut f64, data: &[f64], coeffs: &[f64]) {
-    if !cfg!(feature = "std") {
     *buf = data
         .iter()
         .zip(coeffs.iter())
-        .fold(0.0f64, |acc, (a, b)| a.mul_add(*b, acc));
-    } else {
-    *buf = data
-        .iter()
-        .zip(coeffs.iter())
-        .fold(0.0f64, |acc, (a, b)| a * (*b) + acc);
-    }
+        .fold(0.0f64, |acc, (a, b)| mul_add_f64(*a, *b, acc));
 }
 
 #[inline]
 pub fn dot_prod_update_f32(buf: &mut f32, data: &[f32], coeffs: &[f32]) {
-    if !cfg!(feature = "std") {
-    *buf = data
-        .iter()
-        .zip(coeffs.iter())
-        .fold(0.0f32, |acc, (a, b)| a.mul_add(*b, acc));
-    } else {
     *buf = data
         .iter()
         .zip(coeffs.iter())
-        .fold(0.0f32, |acc, (a, b)| a * (*b) + acc);
-    }
+        .fold(0.0f32, |acc, (a, b)| mul_add_f32(*a, *b, acc));
 }
 
 #[test]
@@ -102,6 +159,38 @@ pub fn sav_gol_f32<const WINDOW: usize, const M: usize>(buf: &mut [f32], data: &
         });
 }
 
+/// `sav_gol` on `f32` samples, with `f64` coefficients and accumulation.
+///
+/// Each sample is widened inside the dot product and only the result is rounded to `f32`, so the
+/// output is `sav_gol` on the widened samples, rounded once. For wide windows on signals with a
+/// large offset this is much closer than `sav_gol_f32`, whose `f32` accumulator rounds every
+/// partial sum.
+/// ```
+///     use staged_sg_filter::sav_gol_mixed;
+///     let v = vec![0.0f32, 3.0, 0.0, 3.0, 0.0];
+///     let mut buf = vec![0.0f32; 5];
+///     sav_gol_mixed::<1, 1>(&mut buf, &v);
+///     assert_eq!(buf[2], 2.0);
+///```
+pub fn sav_gol_mixed<const WINDOW: usize, const M: usize>(buf: &mut [f32], data: &[f32]) {
+    let coeffs = get_coeffs::<WINDOW, M>();
+    let window_size = 2 * WINDOW + 1;
+    let body_size = data.len() - (window_size - 1);
+    buf.iter_mut()
+        // Start the iteration without the `windows` reaching before `buf` starts
+        .skip(window_size / 2)
+        .zip(data.windows(window_size))
+        // Advance `body_size` iterations so that `windows` doesn't go over the end of `buf`
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            *buf = data
+                .iter()
+                .zip(coeffs.iter())
+                .fold(0.0f64, |acc, (a, b)| mul_add_f64(*a as f64, *b, acc))
+                as f32;
+        });
+}
+
 #[test]
 fn test_sav_gol() {
     let v = vec![0.0, 10.0, 0.0, 10.0, 0.0, 10.0, 0.0];
@@ -135,17 +224,525 @@ fn test_sav_golf32() {
     ];
     assert_eq!(res, buf);
 }
+
+#[test]
+fn test_sav_gol_mixed() {
+    // A large offset under a small signal, through a wide window
+    let v: Vec<f32> = (0..300)
+        .map(|i| 3e4 + (i as f32 * 0.05).sin() + (i % 3) as f32 * 0.25)
+        .collect();
+    let wide: Vec<f64> = v.iter().map(|&x| x as f64).collect();
+    let mut ans = vec![0.0; 300];
+    sav_gol::<25, 4>(&mut ans, &wide);
+    let mut buf = vec![-1.0f32; 300];
+    sav_gol_mixed::<25, 4>(&mut buf, &v);
+    (25..275).for_each(|i| assert_eq!(buf[i], ans[i] as f32));
+    assert!(buf[..25].iter().chain(&buf[275..]).all(|&y| y == -1.0));
+
+    let mut narrow = vec![0.0f32; 300];
+    sav_gol_f32::<25, 4>(&mut narrow, &v);
+    let max_err = |res: &[f32]| {
+        (25..275)
+            .map(|i| (res[i] as f64 - ans[i]).abs())
+            .fold(0.0, f64::max)
+    };
+    assert!(max_err(&buf) < max_err(&narrow) / 4.0);
+}
+
+/// Savitzky-Golay filter with a runtime coefficient row
+///
+/// Same as `sav_gol`, but applies `coeffs` instead of a row of `COEFFS`, so any order and
+/// derivative row from `coeffs::fit_coeffs` can use the fast path. `WINDOW` is still a const
+/// generic so the dot product keeps its fixed trip count.
+/// ```
+///     use staged_sg_filter::{sav_gol, sav_gol_with};
+///     use staged_sg_filter::coeffs::get_coeffs;
+///     let v = vec![0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+///     let mut buf = vec![0.0; 7];
+///     let mut res = vec![0.0; 7];
+///     sav_gol_with::<1>(&mut buf, &v, get_coeffs::<1, 1>());
+///     sav_gol::<1, 1>(&mut res, &v);
+///     assert_eq!(res, buf);
+///```
+pub fn sav_gol_with<const WINDOW: usize>(buf: &mut [f64], data: &[f64], coeffs: &[f64]) {
+    let window_size = 2 * WINDOW + 1;
+    // Pin the length of `coeffs` so the trip count stays known at compile time
+    let coeffs = &coeffs[..window_size];
+    let body_size = data.len() - (window_size - 1);
+    buf.iter_mut()
+        // Start the iteration without the `windows` reaching before `buf` starts
+        .skip(window_size / 2)
+        .zip(data.windows(window_size))
+        // Advance `body_size` iterations so that `windows` doesn't go over the end of `buf`
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            dot_prod_update(buf, data, coeffs);
+        });
+}
+
+pub fn sav_gol_with_f32<const WINDOW: usize>(buf: &mut [f32], data: &[f32], coeffs: &[f32]) {
+    let window_size = 2 * WINDOW + 1;
+    // Pin the length of `coeffs` so the trip count stays known at compile time
+    let coeffs = &coeffs[..window_size];
+    let body_size = data.len() - (window_size - 1);
+    buf.iter_mut()
+        // Start the iteration without the `windows` reaching before `buf` starts
+        .skip(window_size / 2)
+        .zip(data.windows(window_size))
+        // Advance `body_size` iterations so that `windows` doesn't go over the end of `buf`
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            dot_prod_update_f32(buf, data, coeffs);
+        });
+}
+
+/// Calls `$kernel::<WINDOW>` for the half-width of `$coeffs` when it is one of the listed
+/// instantiations, and `$fallback` otherwise.
+macro_rules! dispatch_window {
+    ($kernel:ident, $fallback:ident, $buf:expr, $data:expr, $coeffs:expr; $($window:literal)*) => {
+        match $coeffs.len() / 2 {
+            $($window => $kernel::<$window>($buf, $data, $coeffs),)*
+            _ => $fallback($buf, $data, $coeffs),
+        }
+    };
+}
+
+/// Savitzky-Golay filter with a runtime coefficient row of any (odd) length
+///
+/// Rows of `fft::CROSSOVER` points and more are filtered by FFT convolution with `fft::sav_gol_fft`
+/// (with the `std` feature), shorter ones with `sav_gol_direct`.
+/// The fringes of `buf` are left untouched, see `boundary::fill_fringes` to fill them.
+pub fn sav_gol_dyn(buf: &mut [f64], data: &[f64], coeffs: &[f64]) {
+    #[cfg(feature = "std")]
+    if coeffs.len() >= fft::CROSSOVER {
+        return fft::sav_gol_fft(buf, data, coeffs);
+    }
+    sav_gol_direct(buf, data, coeffs);
+}
+
+pub fn sav_gol_dyn_f32(buf: &mut [f32], data: &[f32], coeffs: &[f32]) {
+    #[cfg(feature = "std")]
+    if coeffs.len() >= fft::CROSSOVER {
+        return fft::sav_gol_fft_f32(buf, data, coeffs);
+    }
+    sav_gol_direct_f32(buf, data, coeffs);
+}
+
+/// `sav_gol_dyn` with the direct dot product for every row length
+///
+/// Dispatches to the `sav_gol_with::<WINDOW>` instantiation matching `coeffs` for the half-widths
+/// of `COEFFS` (`1..=25`), and to a runtime-length loop for wider rows.
+pub fn sav_gol_direct(buf: &mut [f64], data: &[f64], coeffs: &[f64]) {
+    if coeffs.len().is_multiple_of(2) {
+        panic!("coeffs must hold an odd number of elements");
+    }
+    dispatch_window!(sav_gol_with, sav_gol_runtime, buf, data, coeffs;
+        1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25)
+}
+
+pub fn sav_gol_direct_f32(buf: &mut [f32], data: &[f32], coeffs: &[f32]) {
+    if coeffs.len().is_multiple_of(2) {
+        panic!("coeffs must hold an odd number of elements");
+    }
+    dispatch_window!(sav_gol_with_f32, sav_gol_runtime_f32, buf, data, coeffs;
+        1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25)
+}
+
+fn sav_gol_runtime(buf: &mut [f64], data: &[f64], coeffs: &[f64]) {
+    let window_size = coeffs.len();
+    let body_size = data.len() - (window_size - 1);
+    buf.iter_mut()
+        .skip(window_size / 2)
+        .zip(data.windows(window_size))
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            dot_prod_update(buf, data, coeffs);
+        });
+}
+
+fn sav_gol_runtime_f32(buf: &mut [f32], data: &[f32], coeffs: &[f32]) {
+    let window_size = coeffs.len();
+    let body_size = data.len() - (window_size - 1);
+    buf.iter_mut()
+        .skip(window_size / 2)
+        .zip(data.windows(window_size))
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            dot_prod_update_f32(buf, data, coeffs);
+        });
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_sav_gol_dyn() {
+    let v: Vec<f64> = (0..100).map(|i| (i as f64 * 0.1).sin()).collect();
+    for half_width in [1, 2, 7, 25, 26, 40] {
+        let coeffs = coeffs::fit_coeffs(half_width, 2, 1, 0.0);
+        let mut buf = vec![f64::NAN; 100];
+        sav_gol_direct(&mut buf, &v, &coeffs);
+        let mut res = vec![f64::NAN; 100];
+        sav_gol_runtime(&mut res, &v, &coeffs);
+        assert_eq!(
+            res[half_width..100 - half_width],
+            buf[half_width..100 - half_width]
+        );
+        assert!(buf[..half_width].iter().all(|b| b.is_nan()));
+        // The wide rows go through the FFT
+        sav_gol_dyn(&mut buf, &v, &coeffs);
+        (half_width..100 - half_width).for_each(|i| assert!((buf[i] - res[i]).abs() < 1e-12));
+        assert!(buf[..half_width].iter().all(|b| b.is_nan()));
+    }
+    let mut buf = vec![0.0; 100];
+    let mut res = vec![0.0; 100];
+    sav_gol_dyn(&mut buf, &v, get_coeffs::<2, 2>());
+    sav_gol::<2, 2>(&mut res, &v);
+    assert_eq!(res, buf);
+}
+
+#[test]
+fn test_sav_gol_dyn_f32() {
+    let v: Vec<f32> = (0..100).map(|i| (i as f32 * 0.1).sin()).collect();
+    let mut buf = vec![0.0f32; 100];
+    let mut res = vec![0.0f32; 100];
+    sav_gol_dyn_f32(&mut buf, &v, get_coeffs_f32::<2, 2>());
+    sav_gol_f32::<2, 2>(&mut res, &v);
+    assert_eq!(res, buf);
+}
+
+#[test]
+#[should_panic(expected = "odd number")]
+fn test_sav_gol_dyn_even() {
+    sav_gol_dyn(&mut [0.0; 8], &[0.0; 8], &[0.25; 4]);
+}
+
+/// Savitzky-Golay smoothing and derivatives in a single pass
+///
+/// Writes the smoothed signal and its derivatives `1..N` into an array per element of `buf`, so
+/// that `buf[i][d]` is the `d`-th derivative (per sample) of the fit around `data[i]`.
+///
+/// Every window of `data` is loaded once and the `N` coefficient rows are applied to it back to
+/// back, which saves `N - 1` passes over memory compared to calling separate filters.
+/// As with `sav_gol`, the fringes of `buf` are left untouched.
+/// ```
+///     use staged_sg_filter::sav_gol_derivs;
+///     let v: Vec<f64> = (0..7).map(|i| (i * i) as f64).collect();
+///     let mut buf = vec![[0.0; 3]; 7];
+///     sav_gol_derivs::<2, 2, 3>(&mut buf, &v);
+///     assert!((buf[3][0] - 9.0).abs() < 1e-9);
+///     assert!((buf[3][1] - 6.0).abs() < 1e-9);
+///     assert!((buf[3][2] - 2.0).abs() < 1e-9);
+///```
+#[cfg(feature = "std")]
+pub fn sav_gol_derivs<const WINDOW: usize, const M: usize, const N: usize>(
+    buf: &mut [[f64; N]],
+    data: &[f64],
+) {
+    let coeffs: [Vec<f64>; N] = core::array::from_fn(get_deriv_coeffs::<WINDOW, M>);
+    let window_size = 2 * WINDOW + 1;
+    let body_size = data.len() - (window_size - 1);
+    buf.iter_mut()
+        // Start the iteration without the `windows` reaching before `buf` starts
+        .skip(window_size / 2)
+        .zip(data.windows(window_size))
+        // Advance `body_size` iterations so that `windows` doesn't go over the end of `buf`
+        .take(body_size)
+        .for_each(|(buf, data)| {
+            buf.iter_mut()
+                .zip(coeffs.iter())
+                .for_each(|(buf, coeffs)| dot_prod_update(buf, data, coeffs));
+        });
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_sav_gol_derivs() {
+    let v: Vec<f64> = (0..50).map(|i| (i as f64 * 0.3).sin()).collect();
+    let mut buf = vec![[f64::NAN; 3]; 50];
+    sav_gol_derivs::<2, 2, 3>(&mut buf, &v);
+
+    let mut smooth = vec![f64::NAN; 50];
+    sav_gol::<2, 2>(&mut smooth, &v);
+    let coeffs: Vec<Vec<f64>> = (0..3).map(get_deriv_coeffs::<2, 2>).collect();
+    buf[2..48].iter().zip(v.windows(5)).for_each(|(buf, data)| {
+        buf.iter().zip(coeffs.iter()).for_each(|(b, coeffs)| {
+            let mut res = 0.0;
+            dot_prod_update(&mut res, data, coeffs);
+            assert_eq!(*b, res);
+        });
+    });
+    (2..48).for_each(|i| assert!((buf[i][0] - smooth[i]).abs() < 1e-12));
+    assert!(buf[..2]
+        .iter()
+        .chain(buf[48..].iter())
+        .flatten()
+        .all(|b| b.is_nan()));
+}
+
+/// Savitzky-Golay extrapolation
+///
+/// Fits a polynomial of order `M` to the last `2 * WINDOW + 1` elements of `data` and evaluates it
+/// on the `horizon` samples that follow the end of `data`.
+///
+/// Useful for filling small gaps or anticipating the next readings of a sensor.
+/// ```
+///     use staged_sg_filter::sav_gol_predict;
+///     let v = vec![0.0, 1.0, 4.0, 9.0, 16.0];
+///     let res = sav_gol_predict::<2, 2>(&v, 2);
+///     assert!((res[0] - 25.0).abs() < 1e-9);
+///     assert!((res[1] - 36.0).abs() < 1e-9);
+///```
+#[cfg(feature = "std")]
+pub fn sav_gol_predict<const WINDOW: usize, const M: usize>(
+    data: &[f64],
+    horizon: usize,
+) -> Vec<f64> {
+    let window_size = 2 * WINDOW + 1;
+    if data.len() < window_size {
+        panic!("data must hold at least 2 * WINDOW + 1 elements");
+    }
+    let last = &data[data.len() - window_size..];
+    (1..=horizon)
+        .map(|h| {
+            let mut buf = 0.0;
+            dot_prod_update(&mut buf, last, &get_predict_coeffs::<WINDOW, M>(h));
+            buf
+        })
+        .collect()
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_sav_gol_predict() {
+    // A cubic is reproduced exactly by a cubic fit
+    let f = |x: f64| 0.5 * x * x * x - 2.0 * x + 1.0;
+    let v: Vec<f64> = (0..20).map(|i| f(i as f64)).collect();
+    let res = sav_gol_predict::<3, 3>(&v, 3);
+    res.iter()
+        .enumerate()
+        .for_each(|(h, r)| assert!((r - f((20 + h) as f64)).abs() < 1e-6));
+}
+
+#[cfg(feature = "std")]
+#[test]
+#[should_panic(expected = "data must hold")]
+fn test_sav_gol_predict_short() {
+    sav_gol_predict::<2, 1>(&[1.0, 2.0], 1);
+}
+
+/// Savitzky-Golay upsampling
+///
+/// Writes `factor` samples per element of `data` into `buf`, so that `buf[i * factor + q]` is the
+/// local polynomial fit around `data[i]` evaluated at `i + q / factor`.
+///
+/// Each of the `factor` phases is its own coefficient row applied over the same rolling window.
+/// Like `sav_gol`, elements whose window reaches past the fringes of `data` are left untouched.
+/// ```
+///     use staged_sg_filter::sav_gol_resample;
+///     let v = vec![0.0, 1.0, 2.0, 3.0, 4.0];
+///     let mut buf = vec![0.0; 10];
+///     sav_gol_resample::<1, 1>(&mut buf, &v, 2);
+///     assert!((buf[3] - 1.5).abs() < 1e-12);
+///     assert!((buf[6] - 3.0).abs() < 1e-12);
+///```
+#[cfg(feature = "std")]
+pub fn sav_gol_resample<const WINDOW: usize, const M: usize>(
+    buf: &mut [f64],
+    data: &[f64],
+    factor: usize,
+) {
+    if buf.len() != data.len() * factor {
+        panic!("buf must hold factor * data.len() elements");
+    }
+    let window_size = 2 * WINDOW + 1;
+    let body_size = data.len() - (window_size - 1);
+    for phase in 0..factor {
+        let coeffs = get_offset_coeffs::<WINDOW, M>(phase as f64 / factor as f64);
+        buf.iter_mut()
+            // Start at the first element whose window doesn't reach before `data` starts
+            .skip(WINDOW * factor + phase)
+            .step_by(factor)
+            .zip(data.windows(window_size))
+            .take(body_size)
+            .for_each(|(buf, data)| {
+                dot_prod_update(buf, data, &coeffs);
+            });
+    }
+}
+
+/// Savitzky-Golay evaluation at arbitrary positions
+///
+/// Writes into `buf[j]` the local polynomial fit of order `M` evaluated at the (possibly
+/// fractional) index `positions[j]` of `data`.
+///
+/// Each position uses the window centred on its nearest sample. Near the fringes the window is
+/// clamped inside `data` and the fit is evaluated off-centre instead, extrapolating for positions
+/// outside of `data`.
+/// ```
+///     use staged_sg_filter::sav_gol_resample_at;
+///     let v = vec![0.0, 2.0, 4.0, 6.0, 8.0];
+///     let mut buf = vec![0.0; 2];
+///     sav_gol_resample_at::<1, 1>(&mut buf, &v, &[0.37, 2.5]);
+///     assert!((buf[0] - 0.74).abs() < 1e-12);
+///     assert!((buf[1] - 5.0).abs() < 1e-12);
+///```
+#[cfg(feature = "std")]
+pub fn sav_gol_resample_at<const WINDOW: usize, const M: usize>(
+    buf: &mut [f64],
+    data: &[f64],
+    positions: &[f64],
+) {
+    if buf.len() != positions.len() {
+        panic!("buf must hold positions.len() elements");
+    }
+    let window_size = 2 * WINDOW + 1;
+    if data.len() < window_size {
+        panic!("data must hold at least 2 * WINDOW + 1 elements");
+    }
+    let last_centre = data.len() - 1 - WINDOW;
+    buf.iter_mut()
+        .zip(positions.iter())
+        .for_each(|(buf, &pos)| {
+            let centre = (pos.round().max(0.0) as usize).clamp(WINDOW, last_centre);
+            let coeffs = get_offset_coeffs::<WINDOW, M>(pos - centre as f64);
+            dot_prod_update(buf, &data[centre - WINDOW..=centre + WINDOW], &coeffs);
+        });
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_sav_gol_resample() {
+    let f = |x: f64| x * x - 3.0 * x;
+    let v: Vec<f64> = (0..10).map(|i| f(i as f64)).collect();
+    let mut buf = vec![f64::NAN; 40];
+    sav_gol_resample::<2, 2>(&mut buf, &v, 4);
+    // Fringes are untouched
+    assert!(buf[..8].iter().all(|b| b.is_nan()));
+    assert!(buf[32..].iter().all(|b| b.is_nan()));
+    buf[8..32]
+        .iter()
+        .enumerate()
+        .for_each(|(j, b)| assert!((b - f(2.0 + j as f64 / 4.0)).abs() < 1e-9));
+
+    // Phase 0 is plain smoothing
+    let mut smooth = vec![0.0; 10];
+    sav_gol::<1, 1>(&mut smooth, &v);
+    let mut buf = vec![0.0; 30];
+    sav_gol_resample::<1, 1>(&mut buf, &v, 3);
+    (1..9).for_each(|i| assert!((buf[3 * i] - smooth[i]).abs() < 1e-12));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_sav_gol_resample_at() {
+    let f = |x: f64| 0.25 * x * x * x - x;
+    let v: Vec<f64> = (0..12).map(|i| f(i as f64)).collect();
+    let positions = [0.0, 0.37, 3.63, 5.5, 10.99, 12.5];
+    let mut buf = vec![0.0; positions.len()];
+    sav_gol_resample_at::<3, 3>(&mut buf, &v, &positions);
+    buf.iter()
+        .zip(positions.iter())
+        .for_each(|(b, &p)| assert!((b - f(p)).abs() < 1e-9));
+}
+
+/// Savitzky-Golay filter over `channels` interleaved signals
+///
+/// `data` holds frames of `channels` samples, as in multi-channel audio, and every channel is
+/// filtered with `sav_gol` on its own. The fringes of each channel in `buf` are left untouched.
+/// ```
+///     use staged_sg_filter::sav_gol_interleaved;
+///     // Left channel is a ramp, right channel alternates
+///     let v: Vec<f64> = (0..7).flat_map(|i| [i as f64, (i % 2) as f64]).collect();
+///     let mut buf = vec![0.0; 14];
+///     sav_gol_interleaved::<1, 1>(&mut buf, &v, 2);
+///     assert!((buf[6] - 3.0).abs() < 1e-12);
+///     assert!((buf[7] - 1.0 / 3.0).abs() < 1e-12);
+///```
+#[cfg(feature = "std")]
+pub fn sav_gol_interleaved<const WINDOW: usize, const M: usize>(
+    buf: &mut [f64],
+    data: &[f64],
+    channels: usize,
+) {
+    interleaved(buf, data, channels, sav_gol::<WINDOW, M>)
+}
+
+#[cfg(feature = "std")]
+pub fn sav_gol_interleaved_f32<const WINDOW: usize, const M: usize>(
+    buf: &mut [f32],
+    data: &[f32],
+    channels: usize,
+) {
+    interleaved(buf, data, channels, sav_gol_f32::<WINDOW, M>)
+}
+
+/// Runs `filter` over each channel of the interleaved `data`, through a scratch buffer per channel
+#[cfg(feature = "std")]
+fn interleaved<T: Copy + Default>(
+    buf: &mut [T],
+    data: &[T],
+    channels: usize,
+    filter: impl Fn(&mut [T], &[T]),
+) {
+    if channels == 0 || !data.len().is_multiple_of(channels) {
+        panic!("data must hold a whole number of frames of channels samples");
+    }
+    let frames = data.len() / channels;
+    let mut lane = vec![T::default(); frames];
+    let mut out = vec![T::default(); frames];
+    for ch in 0..channels {
+        lane.iter_mut()
+            .zip(data.iter().skip(ch).step_by(channels))
+            .for_each(|(l, x)| *l = *x);
+        out.iter_mut()
+            .zip(buf.iter().skip(ch).step_by(channels))
+            .for_each(|(o, b)| *o = *b);
+        filter(&mut out, &lane);
+        buf.iter_mut()
+            .skip(ch)
+            .step_by(channels)
+            .zip(out.iter())
+            .for_each(|(b, o)| *b = *o);
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_sav_gol_interleaved() {
+    let left: Vec<f32> = (0..20).map(|i| (i as f32 * 0.3).sin()).collect();
+    let right: Vec<f32> = (0..20).map(|i| ((i * 7) % 5) as f32).collect();
+    let v: Vec<f32> = left
+        .iter()
+        .zip(right.iter())
+        .flat_map(|(l, r)| [*l, *r])
+        .collect();
+    let mut buf = vec![-1.0; 40];
+    sav_gol_interleaved_f32::<3, 2>(&mut buf, &v, 2);
+    for (ch, signal) in [left, right].iter().enumerate() {
+        let mut ans = vec![-1.0; 20];
+        sav_gol_f32::<3, 2>(&mut ans, signal);
+        let res: Vec<f32> = buf.iter().skip(ch).step_by(2).copied().collect();
+        assert_eq!(res, ans);
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+#[should_panic(expected = "whole number of frames")]
+fn test_sav_gol_interleaved_partial_frame() {
+    sav_gol_interleaved::<1, 1>(&mut [0.0; 7], &[0.0; 7], 2);
+}
+
 // dynamic data (must accept args)
 // mark as #[inline(never)]
 // cargo asm --lib
 #[inline(never)]
 pub fn asm_dump_f64(buf: &mut [f64], data: &mut [f64]) {
-    sav_gol::<2, 2>(buf, &data);
+    sav_gol::<2, 2>(buf, data);
 }
 
 #[inline(never)]
 pub fn asm_dump_f32(buf: &mut [f32], data: &mut [f32]) {
-    sav_gol_f32::<2, 2>(buf, &data);
+    sav_gol_f32::<2, 2>(buf, data);
 }
 
 #[cfg(feature = "rayon")]
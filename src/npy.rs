@@ -0,0 +1,238 @@
+//! Dependency-free reading and writing of NumPy `.npy` files.
+//!
+//! Only what is needed to trade signals with Python is supported: little-endian `<f4` and `<f8`
+//! arrays in C order with one or two dimensions.
+
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Elements of an array, in the dtype they were stored with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NpyData {
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+}
+
+/// A C-ordered array with one or two dimensions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Npy {
+    pub shape: Vec<usize>,
+    pub data: NpyData,
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Value following `'key':` in the header dictionary, up to the next top-level `,` or `}`
+fn header_value<'a>(header: &'a str, key: &str) -> io::Result<&'a str> {
+    let start = header
+        .find(&format!("'{key}'"))
+        .ok_or_else(|| invalid(format!("npy header is missing `{key}`")))?;
+    let rest = header[start + key.len() + 2..].trim_start();
+    let rest = rest
+        .strip_prefix(':')
+        .ok_or_else(|| invalid("malformed npy header"))?
+        .trim_start();
+    // The shape tuple holds commas of its own
+    let end = match rest.starts_with('(') {
+        true => rest.find(')').map(|i| i + 1),
+        false => rest.find([',', '}']),
+    }
+    .ok_or_else(|| invalid("malformed npy header"))?;
+    Ok(rest[..end].trim())
+}
+
+fn parse_header(header: &str) -> io::Result<(bool, Vec<usize>)> {
+    let descr = header_value(header, "descr")?.trim_matches(|c| c == '\'' || c == '"');
+    let is_f64 = match descr {
+        "<f4" => false,
+        "<f8" => true,
+        _ => {
+            return Err(invalid(format!(
+                "unsupported npy dtype `{descr}`, expected <f4 or <f8"
+            )))
+        }
+    };
+    if header_value(header, "fortran_order")? != "False" {
+        return Err(invalid("Fortran ordered npy arrays are not supported"));
+    }
+    let shape = header_value(header, "shape")?
+        .trim_matches(|c| c == '(' || c == ')')
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse()
+                .map_err(|_| invalid(format!("invalid npy shape `{s}`")))
+        })
+        .collect::<io::Result<Vec<usize>>>()?;
+    if !(1..=2).contains(&shape.len()) {
+        return Err(invalid(format!(
+            "npy arrays must have 1 or 2 dimensions, found {}",
+            shape.len()
+        )));
+    }
+    Ok((is_f64, shape))
+}
+
+/// Reads a `.npy` array of `<f4` or `<f8` elements.
+pub fn read_npy(mut r: impl Read) -> io::Result<Npy> {
+    let mut magic = [0u8; 8];
+    r.read_exact(&mut magic)?;
+    if &magic[..6] != MAGIC {
+        return Err(invalid("not an npy file"));
+    }
+    let header_len = match magic[6] {
+        1 => {
+            let mut len = [0u8; 2];
+            r.read_exact(&mut len)?;
+            u16::from_le_bytes(len) as usize
+        }
+        2 | 3 => {
+            let mut len = [0u8; 4];
+            r.read_exact(&mut len)?;
+            u32::from_le_bytes(len) as usize
+        }
+        v => return Err(invalid(format!("unsupported npy version {v}"))),
+    };
+    let mut header = vec![0u8; header_len];
+    r.read_exact(&mut header)?;
+    let header = String::from_utf8(header).map_err(|_| invalid("npy header is not utf-8"))?;
+    let (is_f64, shape) = parse_header(&header)?;
+
+    // The header is untrusted, so the size is checked and the buffer only grows as data arrives
+    let size = shape
+        .iter()
+        .try_fold(if is_f64 { 8 } else { 4 }, |acc: usize, n| {
+            acc.checked_mul(*n)
+        })
+        .and_then(|size| u64::try_from(size).ok())
+        .ok_or_else(|| invalid(format!("npy shape {shape:?} is too large")))?;
+    let mut bytes = vec![];
+    r.take(size).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < size {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("npy data ends after {} of {size} bytes", bytes.len()),
+        ));
+    }
+    let data = match is_f64 {
+        true => NpyData::F64(
+            bytes
+                .chunks_exact(8)
+                .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+                .collect(),
+        ),
+        false => NpyData::F32(
+            bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                .collect(),
+        ),
+    };
+    Ok(Npy { shape, data })
+}
+
+/// Writes `npy` as a version 1.0 `.npy` file.
+pub fn write_npy(mut w: impl Write, npy: &Npy) -> io::Result<()> {
+    let (descr, len) = match &npy.data {
+        NpyData::F32(v) => ("<f4", v.len()),
+        NpyData::F64(v) => ("<f8", v.len()),
+    };
+    if npy.shape.iter().product::<usize>() != len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "npy shape doesn't match the number of elements",
+        ));
+    }
+    let shape = match npy.shape.as_slice() {
+        [n] => format!("({n},)"),
+        dims => format!(
+            "({})",
+            dims.iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+    // Pad with spaces so the data starts on a 64 byte boundary, ending the header with a newline
+    let unpadded = MAGIC.len() + 2 + 2 + header.len() + 1;
+    header.extend(std::iter::repeat_n(' ', (64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    w.write_all(MAGIC)?;
+    w.write_all(&[1, 0])?;
+    w.write_all(&(header.len() as u16).to_le_bytes())?;
+    w.write_all(header.as_bytes())?;
+    match &npy.data {
+        NpyData::F32(v) => v.iter().try_for_each(|x| w.write_all(&x.to_le_bytes()))?,
+        NpyData::F64(v) => v.iter().try_for_each(|x| w.write_all(&x.to_le_bytes()))?,
+    }
+    w.flush()
+}
+
+#[test]
+fn test_npy_round_trip() {
+    for npy in [
+        Npy {
+            shape: vec![3],
+            data: NpyData::F64(vec![1.0, -2.5, 3e10]),
+        },
+        Npy {
+            shape: vec![2, 3],
+            data: NpyData::F32(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.5]),
+        },
+    ] {
+        let mut bytes = vec![];
+        write_npy(&mut bytes, &npy).unwrap();
+        let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        assert_eq!(read_npy(bytes.as_slice()).unwrap(), npy);
+    }
+}
+
+#[test]
+fn test_read_npy() {
+    // `np.save(f, np.arange(4, dtype='<f4').reshape(2, 2))` as written by NumPy 1.26
+    let mut bytes = b"\x93NUMPY\x01\x00\x76\x00".to_vec();
+    let header = "{'descr': '<f4', 'fortran_order': False, 'shape': (2, 2), }";
+    bytes.extend(header.bytes());
+    bytes.extend(std::iter::repeat_n(b' ', 0x76 - 1 - header.len()));
+    bytes.push(b'\n');
+    [0.0f32, 1.0, 2.0, 3.0]
+        .iter()
+        .for_each(|x| bytes.extend(x.to_le_bytes()));
+    let npy = read_npy(bytes.as_slice()).unwrap();
+    assert_eq!(npy.shape, vec![2, 2]);
+    assert_eq!(npy.data, NpyData::F32(vec![0.0, 1.0, 2.0, 3.0]));
+}
+
+#[test]
+fn test_read_npy_errors() {
+    let header = |h: &str| {
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend((h.len() as u16).to_le_bytes());
+        bytes.extend(h.bytes());
+        bytes.extend([0u8; 64]);
+        read_npy(bytes.as_slice()).unwrap_err().to_string()
+    };
+    assert!(header("{'descr': '>f8', 'fortran_order': False, 'shape': (2,), }").contains("dtype"));
+    assert!(header("{'descr': '<i4', 'fortran_order': False, 'shape': (2,), }").contains("dtype"));
+    assert!(header("{'descr': '<f8', 'fortran_order': True, 'shape': (2,), }").contains("Fortran"));
+    assert!(
+        header("{'descr': '<f8', 'fortran_order': False, 'shape': (1, 2, 2), }")
+            .contains("dimensions")
+    );
+    assert!(read_npy(&b"PK\x03\x04 not npy"[..]).is_err());
+    // Sizes that overflow, or that the data doesn't back, fail before allocating them
+    let huge = format!(
+        "{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, 2), }}",
+        usize::MAX / 2
+    );
+    assert!(header(&huge).contains("too large"));
+    let short = "{'descr': '<f8', 'fortran_order': False, 'shape': (1000000000000,), }";
+    assert!(header(short).contains("ends after 64"));
+}
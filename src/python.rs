@@ -0,0 +1,112 @@
+//! Python bindings mirroring `scipy.signal`, behind the `python` feature.
+//!
+//! `savgol_filter` takes the same arguments as SciPy's and works on NumPy arrays of any
+//! dimension. The input is read in place and the output is written straight into the returned
+//! array; lanes along a strided `axis` go through a scratch buffer so the kernels see contiguous
+//! slices. `float32` arrays are filtered in `f32`, everything else is converted to `float64`
+//! first, as SciPy does. Build a wheel with `maturin build --release`.
+
+use crate::scipy::{
+    parse_mode, savgol_coeffs as coeffs, savgol_filter as filter, savgol_filter_f32,
+};
+use numpy::ndarray::{ArrayViewD, ArrayViewMutD, Axis};
+use numpy::prelude::*;
+use numpy::{PyArray1, PyArrayDyn, PyReadonlyArrayDyn};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Runs `filter` over every lane of `x` along `axis`, writing the matching lane of `out`
+fn filter_lanes<T: Copy + Default>(
+    x: ArrayViewD<T>,
+    mut out: ArrayViewMutD<T>,
+    axis: isize,
+    filter: impl Fn(&mut [T], &[T]) -> Result<(), String>,
+) -> PyResult<()> {
+    let ndim = x.ndim() as isize;
+    if !(-ndim..ndim).contains(&axis) {
+        return Err(PyValueError::new_err(format!(
+            "axis {axis} is out of bounds for array of dimension {ndim}"
+        )));
+    }
+    let axis = Axis(axis.rem_euclid(ndim) as usize);
+    let n = x.len_of(axis);
+    let (mut src, mut dst) = (vec![T::default(); n], vec![T::default(); n]);
+    for (lane, mut lane_out) in x.lanes(axis).into_iter().zip(out.lanes_mut(axis)) {
+        let res = match (lane.as_slice(), lane_out.as_slice_mut()) {
+            (Some(x), Some(out)) => Some(filter(out, x)),
+            _ => None,
+        };
+        let res = res.unwrap_or_else(|| {
+            src.iter_mut().zip(lane.iter()).for_each(|(s, x)| *s = *x);
+            let res = filter(&mut dst, &src);
+            lane_out
+                .iter_mut()
+                .zip(dst.iter())
+                .for_each(|(o, d)| *o = *d);
+            res
+        });
+        res.map_err(PyValueError::new_err)?;
+    }
+    Ok(())
+}
+
+/// Savitzky-Golay filter, a drop-in for `scipy.signal.savgol_filter` with an odd `window_length`.
+#[pyfunction]
+#[pyo3(signature = (x, window_length, polyorder, deriv=0, delta=1.0, axis=-1, mode="interp", cval=0.0))]
+#[allow(clippy::too_many_arguments)]
+fn savgol_filter<'py>(
+    py: Python<'py>,
+    x: &Bound<'py, PyAny>,
+    window_length: usize,
+    polyorder: usize,
+    deriv: usize,
+    delta: f64,
+    axis: isize,
+    mode: &str,
+    cval: f64,
+) -> PyResult<Bound<'py, PyAny>> {
+    let mode = parse_mode(mode, cval).map_err(PyValueError::new_err)?;
+    if let Ok(x) = x.extract::<PyReadonlyArrayDyn<'py, f32>>() {
+        let out = PyArrayDyn::<f32>::zeros(py, x.shape(), false);
+        filter_lanes(
+            x.as_array(),
+            out.readwrite().as_array_mut(),
+            axis,
+            |out, x| savgol_filter_f32(out, x, window_length, polyorder, deriv, delta, mode),
+        )?;
+        return Ok(out.into_any());
+    }
+    let x = py
+        .import("numpy")?
+        .call_method1("asarray", (x, "float64"))?;
+    let x = x.extract::<PyReadonlyArrayDyn<'py, f64>>()?;
+    let out = PyArrayDyn::<f64>::zeros(py, x.shape(), false);
+    filter_lanes(
+        x.as_array(),
+        out.readwrite().as_array_mut(),
+        axis,
+        |out, x| filter(out, x, window_length, polyorder, deriv, delta, mode),
+    )?;
+    Ok(out.into_any())
+}
+
+/// Filter coefficients in convolution order, like `scipy.signal.savgol_coeffs`.
+#[pyfunction]
+#[pyo3(signature = (window_length, polyorder, deriv=0, delta=1.0))]
+fn savgol_coeffs(
+    py: Python<'_>,
+    window_length: usize,
+    polyorder: usize,
+    deriv: usize,
+    delta: f64,
+) -> PyResult<Bound<'_, PyArray1<f64>>> {
+    let c = coeffs(window_length, polyorder, deriv, delta).map_err(PyValueError::new_err)?;
+    Ok(PyArray1::from_vec(py, c))
+}
+
+#[pymodule]
+fn staged_sg_filter(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(savgol_filter, m)?)?;
+    m.add_function(wrap_pyfunction!(savgol_coeffs, m)?)?;
+    Ok(())
+}
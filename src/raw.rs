@@ -0,0 +1,401 @@
+//! Block-wise filtering of raw little-endian `f32`/`f64` signals.
+//!
+//! Signals that don't fit in memory are read `block_len` samples at a time. Each block is
+//! prepended with the last `2 * half_width + 1` samples of the previous one, so every window
+//! sees the same samples it would in memory and the output matches filtering the whole signal at
+//! once. Memory use only depends on `block_len` and the window, never on the size of the signal.
+//!
+//! With the `mmap` feature, `map_raw` maps a file into memory instead. The mapped bytes stream
+//! through the same functions as any other input, so the output and the memory use of the filter
+//! don't change, but blocks are copied straight out of the page cache rather than read with a
+//! system call each, and the pages behind the window are left for the operating system to evict.
+
+use crate::boundary::{fill_fringes, fill_fringes_f32, Mode};
+use crate::coeffs::fit_coeffs;
+use crate::{sav_gol, sav_gol_direct, sav_gol_direct_f32, sav_gol_f32};
+use std::io::{self, Read, Write};
+
+/// Samples that can be stored as raw little-endian bytes
+trait Sample: Copy + Default {
+    const SIZE: usize;
+    fn from_le(bytes: &[u8]) -> Self;
+    fn extend_le(self, out: &mut Vec<u8>);
+}
+
+impl Sample for f64 {
+    const SIZE: usize = 8;
+    fn from_le(bytes: &[u8]) -> Self {
+        f64::from_le_bytes(bytes.try_into().unwrap())
+    }
+    fn extend_le(self, out: &mut Vec<u8>) {
+        out.extend(self.to_le_bytes())
+    }
+}
+
+impl Sample for f32 {
+    const SIZE: usize = 4;
+    fn from_le(bytes: &[u8]) -> Self {
+        f32::from_le_bytes(bytes.try_into().unwrap())
+    }
+    fn extend_le(self, out: &mut Vec<u8>) {
+        out.extend(self.to_le_bytes())
+    }
+}
+
+/// Maps the file at `path` into memory, for streaming its bytes through `filter_raw` and friends.
+/// ```no_run
+///     use staged_sg_filter::raw::{map_raw, sav_gol_raw};
+///     // The file is not written to while mapped
+///     let input = unsafe { map_raw("capture.bin") }.unwrap();
+///     let output = std::fs::File::create("capture_smooth.bin").unwrap();
+///     sav_gol_raw::<12, 4>(&input[..], std::io::BufWriter::new(output), 1 << 16).unwrap();
+///```
+///
+/// # Safety
+/// The file must not be modified or truncated while it is mapped, by this process or another
+/// one. The mapping would change under the filter, and accessing truncated pages raises `SIGBUS`.
+#[cfg(feature = "mmap")]
+pub unsafe fn map_raw(path: impl AsRef<std::path::Path>) -> io::Result<memmap2::Mmap> {
+    let map = memmap2::Mmap::map(&std::fs::File::open(path)?)?;
+    // The samples are read front to back, once
+    #[cfg(unix)]
+    map.advise(memmap2::Advice::Sequential)?;
+    Ok(map)
+}
+
+/// Reads until `buf` is full or the input ends, returning the number of bytes read
+fn read_full(input: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match input.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(read)
+}
+
+/// Streams `input` to `output` through `body`, which filters the body of a block, and `fringes`,
+/// which fills both fringes of a block from its first and last `2 * half_width + 1` samples.
+fn stream<T: Sample>(
+    mut input: impl Read,
+    mut output: impl Write,
+    half_width: usize,
+    block_len: usize,
+    body: impl Fn(&mut [T], &[T]),
+    fringes: impl Fn(&mut [T], &[T]),
+) -> io::Result<u64> {
+    if block_len == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "block_len must be at least 1",
+        ));
+    }
+    let carry = 2 * half_width + 1;
+    let mut data: Vec<T> = Vec::with_capacity(carry + block_len);
+    let mut buf = vec![T::default(); carry + block_len];
+    let mut bytes = vec![0u8; block_len * T::SIZE];
+    let mut out_bytes = Vec::with_capacity((carry + block_len) * T::SIZE);
+    // `data` holds the input samples `start..start + data.len()`, and `done` outputs are written
+    let (mut start, mut done) = (0, 0);
+    loop {
+        let read = read_full(&mut input, &mut bytes)?;
+        if read % T::SIZE != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "input ends in the middle of a sample",
+            ));
+        }
+        data.extend(bytes[..read].chunks_exact(T::SIZE).map(T::from_le));
+        let last = read < bytes.len();
+        if data.len() < carry && !last {
+            continue;
+        }
+        if data.len() < carry {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "input holds fewer samples than the window",
+            ));
+        }
+
+        let buf = &mut buf[..data.len()];
+        body(buf, &data);
+        if start == 0 || last {
+            fringes(buf, &data);
+        }
+        let end = match last {
+            true => data.len(),
+            false => data.len() - half_width,
+        };
+        out_bytes.clear();
+        buf[done - start..end]
+            .iter()
+            .for_each(|x| x.extend_le(&mut out_bytes));
+        output.write_all(&out_bytes)?;
+        done = start + end;
+        if last {
+            output.flush()?;
+            return Ok(done as u64);
+        }
+
+        // Keep the last window around for the next block
+        start += data.len() - carry;
+        data.drain(..data.len() - carry);
+    }
+}
+
+/// Streaming version of `sav_gol` over raw little-endian `f64` samples.
+///
+/// The output is identical to calling `sav_gol::<WINDOW, M>` on the whole signal with a `buf`
+/// holding a copy of it: the fringes are copied through unfiltered. Returns the number of
+/// samples written.
+/// ```
+///     use staged_sg_filter::raw::sav_gol_raw;
+///     use staged_sg_filter::sav_gol;
+///     let v: Vec<f64> = (0..100).map(|i| (i as f64).sqrt()).collect();
+///     let bytes: Vec<u8> = v.iter().flat_map(|x| x.to_le_bytes()).collect();
+///     let mut out = vec![];
+///     sav_gol_raw::<2, 2>(bytes.as_slice(), &mut out, 16).unwrap();
+///
+///     let mut buf = v.clone();
+///     sav_gol::<2, 2>(&mut buf, &v);
+///     let res: Vec<f64> = out.chunks(8).map(|b| f64::from_le_bytes(b.try_into().unwrap())).collect();
+///     assert_eq!(res, buf);
+///```
+pub fn sav_gol_raw<const WINDOW: usize, const M: usize>(
+    input: impl Read,
+    output: impl Write,
+    block_len: usize,
+) -> io::Result<u64> {
+    stream(
+        input,
+        output,
+        WINDOW,
+        block_len,
+        sav_gol::<WINDOW, M>,
+        copy_fringes::<WINDOW, f64>,
+    )
+}
+
+pub fn sav_gol_raw_f32<const WINDOW: usize, const M: usize>(
+    input: impl Read,
+    output: impl Write,
+    block_len: usize,
+) -> io::Result<u64> {
+    stream(
+        input,
+        output,
+        WINDOW,
+        block_len,
+        sav_gol_f32::<WINDOW, M>,
+        copy_fringes::<WINDOW, f32>,
+    )
+}
+
+fn copy_fringes<const WINDOW: usize, T: Copy>(buf: &mut [T], data: &[T]) {
+    let n = data.len();
+    buf[..WINDOW].copy_from_slice(&data[..WINDOW]);
+    buf[n - WINDOW..].copy_from_slice(&data[n - WINDOW..]);
+}
+
+fn check_mode(mode: Mode) -> io::Result<()> {
+    if mode == Mode::Wrap {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "wrap mode needs both ends of the signal at once and can't be streamed",
+        ));
+    }
+    Ok(())
+}
+
+/// Streams raw little-endian `f64` samples through the `deriv`-th derivative filter of an order
+/// `order` fit over `2 * half_width + 1` samples, with the fringes handled by `mode`.
+///
+/// The output matches `sav_gol_direct` followed by `boundary::fill_fringes` on the whole signal,
+/// bit for bit. Wide rows are not filtered by FFT as in `sav_gol_dyn`, whose rounding would
+/// depend on where the blocks start.
+///
+/// `Mode::Wrap` is rejected since it needs the end of the signal to filter its start.
+pub fn filter_raw(
+    input: impl Read,
+    output: impl Write,
+    half_width: usize,
+    order: usize,
+    deriv: usize,
+    mode: Mode,
+    block_len: usize,
+) -> io::Result<u64> {
+    check_mode(mode)?;
+    let coeffs = fit_coeffs(half_width, order, deriv, 0.0);
+    stream(
+        input,
+        output,
+        half_width,
+        block_len,
+        |buf: &mut [f64], data: &[f64]| sav_gol_direct(buf, data, &coeffs),
+        |buf: &mut [f64], data: &[f64]| fill_fringes(buf, data, half_width, order, deriv, mode),
+    )
+}
+
+pub fn filter_raw_f32(
+    input: impl Read,
+    output: impl Write,
+    half_width: usize,
+    order: usize,
+    deriv: usize,
+    mode: Mode,
+    block_len: usize,
+) -> io::Result<u64> {
+    check_mode(mode)?;
+    let coeffs: Vec<f32> = fit_coeffs(half_width, order, deriv, 0.0)
+        .iter()
+        .map(|&c| c as f32)
+        .collect();
+    stream(
+        input,
+        output,
+        half_width,
+        block_len,
+        |buf: &mut [f32], data: &[f32]| sav_gol_direct_f32(buf, data, &coeffs),
+        |buf: &mut [f32], data: &[f32]| fill_fringes_f32(buf, data, half_width, order, deriv, mode),
+    )
+}
+
+#[cfg(test)]
+fn to_bytes(v: &[f64]) -> Vec<u8> {
+    v.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+#[cfg(test)]
+fn from_bytes(bytes: &[u8]) -> Vec<f64> {
+    bytes
+        .chunks_exact(8)
+        .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+        .collect()
+}
+
+#[test]
+fn test_sav_gol_raw() {
+    let v: Vec<f64> = (0..1000)
+        .map(|i| (i as f64 * 0.37).sin() * i as f64)
+        .collect();
+    let mut ans = v.clone();
+    sav_gol::<3, 2>(&mut ans, &v);
+    // Blocks smaller than, equal to and larger than the window, and dividing the signal evenly
+    for block_len in [1, 5, 7, 100, 250, 999, 1000, 4096] {
+        let mut out = vec![];
+        let n = sav_gol_raw::<3, 2>(to_bytes(&v).as_slice(), &mut out, block_len).unwrap();
+        assert_eq!(n, 1000);
+        assert_eq!(from_bytes(&out), ans, "{block_len}");
+    }
+}
+
+#[test]
+fn test_sav_gol_raw_f32() {
+    let v: Vec<f32> = (0..300).map(|i| (i as f32 * 0.1).cos()).collect();
+    let bytes: Vec<u8> = v.iter().flat_map(|x| x.to_le_bytes()).collect();
+    let mut ans = v.clone();
+    sav_gol_f32::<4, 3>(&mut ans, &v);
+    let mut out = vec![];
+    sav_gol_raw_f32::<4, 3>(bytes.as_slice(), &mut out, 64).unwrap();
+    let res: Vec<f32> = out
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+    assert_eq!(res, ans);
+}
+
+#[test]
+fn test_filter_raw() {
+    let v: Vec<f64> = (0..500).map(|i| ((i * 31) % 17) as f64).collect();
+    for mode in [
+        Mode::Interp,
+        Mode::Mirror,
+        Mode::Nearest,
+        Mode::Constant(3.0),
+    ] {
+        let coeffs = fit_coeffs(5, 3, 1, 0.0);
+        let mut ans = vec![0.0; 500];
+        sav_gol_direct(&mut ans, &v, &coeffs);
+        fill_fringes(&mut ans, &v, 5, 3, 1, mode);
+        for block_len in [3, 11, 128, 500] {
+            let mut out = vec![];
+            filter_raw(to_bytes(&v).as_slice(), &mut out, 5, 3, 1, mode, block_len).unwrap();
+            assert_eq!(from_bytes(&out), ans, "{mode:?} {block_len}");
+        }
+    }
+    // A row past `fft::CROSSOVER` stays on the direct dot product, whatever the blocks
+    let v: Vec<f64> = (0..5000)
+        .map(|i| (i as f64 * 0.01).sin() * 100.0 + ((i * 31) % 17) as f64)
+        .collect();
+    let coeffs = fit_coeffs(40, 4, 0, 0.0);
+    assert!(coeffs.len() >= crate::fft::CROSSOVER);
+    let mut ans = vec![0.0; 5000];
+    sav_gol_direct(&mut ans, &v, &coeffs);
+    fill_fringes(&mut ans, &v, 40, 4, 0, Mode::Nearest);
+    for block_len in [7, 128, 1000, 5000] {
+        let mut out = vec![];
+        filter_raw(
+            to_bytes(&v).as_slice(),
+            &mut out,
+            40,
+            4,
+            0,
+            Mode::Nearest,
+            block_len,
+        )
+        .unwrap();
+        assert_eq!(from_bytes(&out), ans, "{block_len}");
+    }
+}
+
+#[test]
+fn test_filter_raw_errors() {
+    let bytes = to_bytes(&[1.0; 10]);
+    let mut out = vec![];
+    let err = |res: io::Result<u64>| res.unwrap_err().kind();
+    assert_eq!(
+        err(filter_raw(&bytes[..], &mut out, 2, 2, 0, Mode::Wrap, 4)),
+        io::ErrorKind::InvalidInput
+    );
+    assert_eq!(
+        err(filter_raw(&bytes[..], &mut out, 2, 2, 0, Mode::Interp, 0)),
+        io::ErrorKind::InvalidInput
+    );
+    assert_eq!(
+        err(filter_raw(&bytes[..], &mut out, 6, 2, 0, Mode::Interp, 4)),
+        io::ErrorKind::InvalidData
+    );
+    assert_eq!(
+        err(filter_raw(&bytes[..77], &mut out, 2, 2, 0, Mode::Interp, 4)),
+        io::ErrorKind::UnexpectedEof
+    );
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_map_raw() {
+    let v: Vec<f64> = (0..2000).map(|i| (i as f64 * 0.02).sin()).collect();
+    let path = std::env::temp_dir().join(format!("sgf_map_raw_{}.bin", std::process::id()));
+    std::fs::write(&path, to_bytes(&v)).unwrap();
+    let map = unsafe { map_raw(&path) }.unwrap();
+    let mut out = vec![];
+    filter_raw(&map[..], &mut out, 8, 3, 0, Mode::Mirror, 300).unwrap();
+    let mut ans = vec![];
+    filter_raw(
+        to_bytes(&v).as_slice(),
+        &mut ans,
+        8,
+        3,
+        0,
+        Mode::Mirror,
+        300,
+    )
+    .unwrap();
+    assert_eq!(out, ans);
+    drop(map);
+    std::fs::remove_file(&path).unwrap();
+    assert!(unsafe { map_raw(&path) }.is_err());
+}
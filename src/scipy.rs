@@ -0,0 +1,196 @@
+//! Savitzky-Golay filtering with the interface of `scipy.signal.savgol_coeffs` and
+//! `scipy.signal.savgol_filter`.
+//!
+//! The parameters follow SciPy: `window_length` is the full (odd) window, `polyorder` the order
+//! of the fit and derivatives are scaled by `delta ** -deriv`. These back the Python bindings of
+//! the `python` feature.
+//!
+//! The outputs are checked against `fixtures/savgol_reference.txt`, computed in exact rational
+//! arithmetic by `fixtures/gen_scipy_fixtures.py` from the least squares definitions SciPy
+//! documents. That is not a comparison with SciPy itself yet: the script takes its values from
+//! SciPy when it is installed and says so, with the version, in the header of the file, so
+//! regenerating it with SciPy turns the test into one.
+
+use crate::boundary::{fill_fringes, fill_fringes_f32, Mode};
+use crate::coeffs::fit_coeffs;
+use crate::{sav_gol_dyn, sav_gol_dyn_f32};
+
+/// Parses a SciPy mode name, `constant` pads with `cval`.
+pub fn parse_mode(mode: &str, cval: f64) -> Result<Mode, String> {
+    match mode.parse()? {
+        Mode::Constant(_) => Ok(Mode::Constant(cval)),
+        mode => Ok(mode),
+    }
+}
+
+/// Half-width of `window_length`, checking it against `polyorder` like SciPy does
+fn half_width(window_length: usize, polyorder: usize) -> Result<usize, String> {
+    if window_length.is_multiple_of(2) {
+        return Err("window_length must be odd.".to_string());
+    }
+    if polyorder >= window_length {
+        return Err("polyorder must be less than window_length.".to_string());
+    }
+    Ok(window_length / 2)
+}
+
+fn check_len(n: usize, window_length: usize, mode: Mode) -> Result<(), String> {
+    if n >= window_length {
+        return Ok(());
+    }
+    Err(match mode {
+        Mode::Interp => {
+            "If mode is 'interp', window_length must be less than or equal to the size of x."
+        }
+        _ => "window_length must be less than or equal to the size of x.",
+    }
+    .to_string())
+}
+
+/// Filter coefficients in convolution order, as returned by `savgol_coeffs(window_length,
+/// polyorder, deriv, delta)`: the first coefficient applies to the last sample of the window.
+/// ```
+///     use staged_sg_filter::scipy::savgol_coeffs;
+///     let c = savgol_coeffs(5, 2, 1, 1.0).unwrap();
+///     assert!((c[0] - 0.2).abs() < 1e-12);
+///     assert!((c[4] + 0.2).abs() < 1e-12);
+///```
+pub fn savgol_coeffs(
+    window_length: usize,
+    polyorder: usize,
+    deriv: usize,
+    delta: f64,
+) -> Result<Vec<f64>, String> {
+    let half_width = half_width(window_length, polyorder)?;
+    let scale = delta.powi(-(deriv as i32));
+    Ok(fit_coeffs(half_width, polyorder, deriv, 0.0)
+        .iter()
+        .rev()
+        .map(|c| c * scale)
+        .collect())
+}
+
+/// `scipy.signal.savgol_filter` over a single axis, writing the whole of `out`.
+///
+/// Unlike SciPy, `window_length` must be odd and no larger than `x` for every mode.
+/// ```
+///     use staged_sg_filter::scipy::savgol_filter;
+///     use staged_sg_filter::boundary::Mode;
+///     let x = [2.0, 2.0, 5.0, 2.0, 1.0, 0.0, 1.0, 4.0, 9.0];
+///     let mut out = [0.0; 9];
+///     savgol_filter(&mut out, &x, 5, 2, 0, 1.0, Mode::Interp).unwrap();
+///     assert!((out[0] - 1.66).abs() < 5e-3);
+///     assert!((out[8] - 9.0).abs() < 1e-9);
+///```
+pub fn savgol_filter(
+    out: &mut [f64],
+    x: &[f64],
+    window_length: usize,
+    polyorder: usize,
+    deriv: usize,
+    delta: f64,
+    mode: Mode,
+) -> Result<(), String> {
+    let half_width = half_width(window_length, polyorder)?;
+    check_len(x.len(), window_length, mode)?;
+    sav_gol_dyn(out, x, &fit_coeffs(half_width, polyorder, deriv, 0.0));
+    fill_fringes(out, x, half_width, polyorder, deriv, mode);
+    if deriv > 0 {
+        let scale = delta.powi(-(deriv as i32));
+        out.iter_mut().for_each(|y| *y *= scale);
+    }
+    Ok(())
+}
+
+pub fn savgol_filter_f32(
+    out: &mut [f32],
+    x: &[f32],
+    window_length: usize,
+    polyorder: usize,
+    deriv: usize,
+    delta: f64,
+    mode: Mode,
+) -> Result<(), String> {
+    let half_width = half_width(window_length, polyorder)?;
+    check_len(x.len(), window_length, mode)?;
+    let coeffs: Vec<f32> = fit_coeffs(half_width, polyorder, deriv, 0.0)
+        .iter()
+        .map(|&c| c as f32)
+        .collect();
+    sav_gol_dyn_f32(out, x, &coeffs);
+    fill_fringes_f32(out, x, half_width, polyorder, deriv, mode);
+    if deriv > 0 {
+        let scale = delta.powi(-(deriv as i32)) as f32;
+        out.iter_mut().for_each(|y| *y *= scale);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_savgol_filter_reference() {
+    let fixtures = include_str!("../fixtures/savgol_reference.txt");
+    let parse =
+        |s: &str| -> Vec<f64> { s.split_whitespace().map(|v| v.parse().unwrap()).collect() };
+    let mut cases = 0;
+    for line in fixtures.lines().filter(|l| !l.starts_with('#')) {
+        let [params, x, expected] = line.split('|').collect::<Vec<_>>()[..] else {
+            panic!("malformed fixture `{line}`");
+        };
+        let p: Vec<&str> = params.split_whitespace().collect();
+        let (wl, po, deriv) = (
+            p[0].parse().unwrap(),
+            p[1].parse().unwrap(),
+            p[2].parse().unwrap(),
+        );
+        let delta: f64 = p[3].parse().unwrap();
+        let mode = parse_mode(p[4], p[5].parse().unwrap()).unwrap();
+        let (x, expected) = (parse(x), parse(expected));
+
+        let mut out = vec![f64::NAN; x.len()];
+        savgol_filter(&mut out, &x, wl, po, deriv, delta, mode).unwrap();
+        out.iter()
+            .zip(expected.iter())
+            .for_each(|(y, e)| assert!((y - e).abs() < 1e-9 * e.abs().max(1.0), "{line}"));
+
+        let x: Vec<f32> = x.iter().map(|&v| v as f32).collect();
+        let mut out = vec![f32::NAN; x.len()];
+        savgol_filter_f32(&mut out, &x, wl, po, deriv, delta, mode).unwrap();
+        out.iter()
+            .zip(expected.iter())
+            .for_each(|(y, e)| assert!((*y as f64 - e).abs() < 1e-3 * e.abs().max(1.0), "{line}"));
+        cases += 1;
+    }
+    assert_eq!(cases, 13);
+}
+
+#[test]
+fn test_savgol_coeffs() {
+    // `savgol_coeffs(5, 2)` from the SciPy documentation
+    let c = savgol_coeffs(5, 2, 0, 1.0).unwrap();
+    [-0.08571429, 0.34285714, 0.48571429, 0.34285714, -0.08571429]
+        .iter()
+        .zip(c.iter())
+        .for_each(|(e, c)| assert!((c - e).abs() < 1e-8));
+    // Derivatives past the order vanish
+    assert!(savgol_coeffs(7, 2, 3, 1.0)
+        .unwrap()
+        .iter()
+        .all(|c| c.abs() < 1e-12));
+}
+
+#[test]
+fn test_savgol_filter_errors() {
+    let mut out = [0.0; 5];
+    let x = [1.0; 5];
+    let call = |wl, po, mode| savgol_filter(&mut out.clone(), &x, wl, po, 0, 1.0, mode);
+    assert_eq!(
+        call(4, 2, Mode::Interp),
+        Err("window_length must be odd.".to_string())
+    );
+    assert!(call(5, 5, Mode::Interp).unwrap_err().contains("polyorder"));
+    assert!(call(7, 2, Mode::Interp).unwrap_err().contains("'interp'"));
+    assert!(call(7, 2, Mode::Wrap).is_err());
+    assert!(savgol_filter(&mut out, &x, 5, 2, 0, 1.0, Mode::Wrap).is_ok());
+    assert_eq!(parse_mode("constant", 2.5), Ok(Mode::Constant(2.5)));
+    assert!(parse_mode("reflect", 0.0).is_err());
+}
@@ -0,0 +1,327 @@
+//! Sliding-moment Savitzky-Golay filter, in constant time per sample for any window.
+//!
+//! For a fit of order `order`, every row is a polynomial of degree `order` in the window
+//! position, so an output is a fixed combination of the window's moments
+//! `S_p = sum_k (k / half_width)^p x_k`. When the window slides by one sample the moments follow
+//! from the previous ones by a binomial shift, minus the sample that left and plus the one that
+//! came in, which takes `O(order^2)` operations however wide the window is.
+//!
+//! The shift carries the rounding errors of the moments along and amplifies them as the window
+//! moves (by up to `(distance / half_width)^order`), so `SlidingSavGol` recomputes the moments from
+//! its window every `resync` samples, once per window by default. That costs one pass over the
+//! window, which keeps the amortized cost per sample independent of the width.
+
+/// Highest order with a sliding update
+pub const MAX_ORDER: usize = 3;
+
+/// Savitzky-Golay filter of a `2 * half_width + 1` window and order `0..=3`, updated from running
+/// moments as samples are pushed.
+/// ```
+///     use staged_sg_filter::sliding::SlidingSavGol;
+///     let mut filter = SlidingSavGol::new(2, 2, 0).unwrap();
+///     let v = [1.0, 4.0, 9.0, 16.0, 25.0, 36.0];
+///     let out: Vec<f64> = v.iter().filter_map(|&x| filter.push(x)).collect();
+///     // A quadratic goes through a quadratic fit unchanged, 2 samples late
+///     assert_eq!(out.len(), 2);
+///     assert!((out[0] - 9.0).abs() < 1e-12 && (out[1] - 16.0).abs() < 1e-12);
+///```
+#[derive(Debug, Clone)]
+pub struct SlidingSavGol {
+    half_width: usize,
+    order: usize,
+    deriv: usize,
+    resync: usize,
+    /// The row is `sum_p poly[p] * (k / half_width)^p`
+    poly: [f64; MAX_ORDER + 1],
+    /// `shift[p][q]` maps the moment `q` before a slide to the moment `p` after it
+    shift: [[f64; MAX_ORDER + 1]; MAX_ORDER + 1],
+    /// `(-1 - 1 / half_width)^p`, the weight of the leaving sample after the shift
+    leaving: [f64; MAX_ORDER + 1],
+    moments: [f64; MAX_ORDER + 1],
+    /// The last `2 * half_width + 1` samples, the oldest at `pos` once full
+    ring: Vec<f64>,
+    pos: usize,
+    filled: usize,
+    since_resync: usize,
+}
+
+impl SlidingSavGol {
+    /// Checks the parameters and computes the moment weights.
+    pub fn new(half_width: usize, order: usize, deriv: usize) -> Result<Self, String> {
+        if half_width == 0 {
+            return Err("half_width must be at least 1".to_string());
+        }
+        if order > MAX_ORDER {
+            return Err(format!("order must be at most {MAX_ORDER}"));
+        }
+        if order > 2 * half_width {
+            return Err(format!(
+                "order must be at most 2 * half_width = {}",
+                2 * half_width
+            ));
+        }
+        let window_size = 2 * half_width + 1;
+        let h = 1.0 / half_width as f64;
+
+        let mut shift = [[0.0; MAX_ORDER + 1]; MAX_ORDER + 1];
+        let mut leaving = [0.0; MAX_ORDER + 1];
+        for p in 0..=order {
+            // (t - h)^p = sum_q C(p, q) (-h)^(p - q) t^q
+            let mut binom = 1.0;
+            for q in (0..=p).rev() {
+                shift[p][q] = binom * (-h).powi((p - q) as i32);
+                binom = binom * q as f64 / (p - q + 1) as f64;
+            }
+            leaving[p] = (-1.0 - h).powi(p as i32);
+        }
+
+        Ok(SlidingSavGol {
+            half_width,
+            order,
+            deriv,
+            resync: window_size,
+            poly: moment_weights(half_width, order, deriv),
+            shift,
+            leaving,
+            moments: [0.0; MAX_ORDER + 1],
+            ring: vec![0.0; window_size],
+            pos: 0,
+            filled: 0,
+            since_resync: 0,
+        })
+    }
+
+    /// Recompute the moments from the window every `period` samples instead of once per window.
+    ///
+    /// # Panics
+    /// If `period` is `0`.
+    pub fn resync_every(mut self, period: usize) -> Self {
+        if period == 0 {
+            panic!("period must be at least 1");
+        }
+        self.resync = period;
+        self
+    }
+
+    pub fn half_width(&self) -> usize {
+        self.half_width
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    pub fn deriv(&self) -> usize {
+        self.deriv
+    }
+
+    /// Forgets the pushed samples.
+    pub fn reset(&mut self) {
+        self.pos = 0;
+        self.filled = 0;
+        self.since_resync = 0;
+    }
+
+    /// Pushes the next sample, returning the output for the sample `half_width` before it once
+    /// the window is full.
+    #[inline]
+    pub fn push(&mut self, x: f64) -> Option<f64> {
+        let window_size = self.ring.len();
+        if self.filled < window_size {
+            self.ring[self.filled] = x;
+            self.filled += 1;
+            if self.filled < window_size {
+                return None;
+            }
+            self.sync();
+        } else {
+            let x_out = std::mem::replace(&mut self.ring[self.pos], x);
+            self.pos = if self.pos + 1 == window_size {
+                0
+            } else {
+                self.pos + 1
+            };
+            self.since_resync += 1;
+            if self.since_resync >= self.resync {
+                self.sync();
+            } else {
+                let old = self.moments;
+                for p in 0..=self.order {
+                    let shifted = (0..=p).fold(0.0, |acc, q| acc + self.shift[p][q] * old[q]);
+                    self.moments[p] = shifted - self.leaving[p] * x_out + x;
+                }
+            }
+        }
+        Some(
+            (0..=self.order)
+                .map(|p| self.poly[p] * self.moments[p])
+                .sum(),
+        )
+    }
+
+    /// Recomputes the moments from the window
+    fn sync(&mut self) {
+        let h = 1.0 / self.half_width as f64;
+        let (newer, older) = self.ring.split_at(self.pos);
+        self.moments = [0.0; MAX_ORDER + 1];
+        for (k, x) in older.iter().chain(newer.iter()).enumerate() {
+            let t = k as f64 * h - 1.0;
+            let mut power = *x;
+            for p in 0..=self.order {
+                self.moments[p] += power;
+                power *= t;
+            }
+        }
+        self.since_resync = 0;
+    }
+
+    /// Filters `data` into `buf` like `sav_gol_dyn`, leaving the fringes of `buf` untouched.
+    ///
+    /// Starts from an empty window, the samples pushed before are forgotten.
+    pub fn apply(&mut self, buf: &mut [f64], data: &[f64]) {
+        self.reset();
+        let half_width = self.half_width;
+        data.iter().enumerate().for_each(|(i, x)| {
+            if let Some(y) = self.push(*x) {
+                buf[i - half_width] = y;
+            }
+        });
+    }
+}
+
+/// Weights of the moments `sum_k (k / half_width)^p x_k` giving the `deriv`-th derivative (per
+/// sample) of the order `order` fit at the centre of the window
+fn moment_weights(half_width: usize, order: usize, deriv: usize) -> [f64; MAX_ORDER + 1] {
+    let mut weights = [0.0; MAX_ORDER + 1];
+    if deriv > order {
+        return weights;
+    }
+    // Normal equations of the fit in `t = k / half_width`, which keeps them well conditioned
+    let n = order + 1;
+    let mut power_sums = [0.0; 2 * MAX_ORDER + 1];
+    for k in 0..=2 * half_width {
+        let t = k as f64 / half_width as f64 - 1.0;
+        let mut power = 1.0;
+        for sum in power_sums.iter_mut().take(2 * order + 1) {
+            *sum += power;
+            power *= t;
+        }
+    }
+    let mut a = [[0.0; MAX_ORDER + 2]; MAX_ORDER + 1];
+    for (p, row) in a.iter_mut().enumerate().take(n) {
+        row[..n].copy_from_slice(&power_sums[p..p + n]);
+        row[n] = if p == deriv { 1.0 } else { 0.0 };
+    }
+    // Gaussian elimination, the matrix is symmetric positive definite so no pivoting is needed
+    for col in 0..n {
+        let (upper, lower) = a.split_at_mut(col + 1);
+        let pivot = &upper[col];
+        for row in lower.iter_mut().take(n - col - 1) {
+            let factor = row[col] / pivot[col];
+            row.iter_mut()
+                .zip(pivot.iter())
+                .skip(col)
+                .for_each(|(r, p)| *r -= factor * p);
+        }
+    }
+    for row in (0..n).rev() {
+        let dot: f64 = (row + 1..n).map(|k| a[row][k] * weights[k]).sum();
+        weights[row] = (a[row][n] - dot) / a[row][row];
+    }
+    // The fit's `deriv`-th coefficient in `t`, as a derivative per sample
+    let scale = (1..=deriv).product::<usize>() as f64 / (half_width as f64).powi(deriv as i32);
+    weights.iter_mut().for_each(|w| *w *= scale);
+    weights
+}
+
+#[test]
+fn test_sliding_sav_gol() {
+    use crate::coeffs::fit_coeffs;
+    use crate::{sav_gol, sav_gol_direct};
+    let v = crate::test_util::noisy_sine(500, 0.05, 4.0, 13, 0.3);
+
+    let mut ans = vec![f64::NAN; 500];
+    sav_gol::<7, 3>(&mut ans, &v);
+    let mut buf = vec![f64::NAN; 500];
+    SlidingSavGol::new(7, 3, 0).unwrap().apply(&mut buf, &v);
+    assert!(buf[..7].iter().chain(&buf[493..]).all(|b| b.is_nan()));
+    (7..493).for_each(|i| assert!((buf[i] - ans[i]).abs() < 1e-12));
+
+    for (half_width, order, deriv) in [
+        (1, 0, 0),
+        (3, 1, 0),
+        (10, 2, 0),
+        (10, 2, 1),
+        (40, 3, 2),
+        (120, 3, 3),
+        (2, 3, 0),
+    ] {
+        let mut ans = vec![f64::NAN; 500];
+        sav_gol_direct(&mut ans, &v, &fit_coeffs(half_width, order, deriv, 0.0));
+        let mut buf = vec![f64::NAN; 500];
+        let mut filter = SlidingSavGol::new(half_width, order, deriv).unwrap();
+        filter.apply(&mut buf, &v);
+        for i in half_width..500 - half_width {
+            assert!(
+                (buf[i] - ans[i]).abs() < 1e-10,
+                "{half_width} {order} {deriv}: {} != {}",
+                buf[i],
+                ans[i]
+            );
+        }
+        // `apply` starts over from an empty window
+        filter.apply(&mut buf, &v);
+        (half_width..500 - half_width).for_each(|i| assert!((buf[i] - ans[i]).abs() < 1e-10));
+    }
+}
+
+#[test]
+fn test_sliding_sav_gol_drift() {
+    use crate::coeffs::fit_coeffs;
+    use crate::dot_prod_update;
+    // A wide window over a long signal with a DC offset
+    let half_width = 300;
+    let len = 200_000;
+    let v: Vec<f64> = crate::test_util::noisy_sine(len, 0.001, 10.0, 101, 0.01)
+        .iter()
+        .map(|x| 1e3 + x)
+        .collect();
+    let coeffs = fit_coeffs(half_width, 3, 0, 0.0);
+    let max_err = |filter: SlidingSavGol| {
+        let mut buf = vec![0.0; len];
+        let mut filter = filter;
+        filter.apply(&mut buf, &v);
+        // Check a spread of outputs against the direct dot product
+        (half_width..len - half_width)
+            .step_by(997)
+            .map(|i| {
+                let mut ans = 0.0;
+                dot_prod_update(&mut ans, &v[i - half_width..=i + half_width], &coeffs);
+                (buf[i] - ans).abs()
+            })
+            .fold(0.0, f64::max)
+    };
+    let synced = max_err(SlidingSavGol::new(half_width, 3, 0).unwrap());
+    let drifting = max_err(
+        SlidingSavGol::new(half_width, 3, 0)
+            .unwrap()
+            .resync_every(usize::MAX),
+    );
+    assert!(synced < 2e-10, "{synced}");
+    assert!(drifting > 100.0 * synced, "{drifting} {synced}");
+}
+
+#[test]
+fn test_sliding_sav_gol_new() {
+    assert!(SlidingSavGol::new(0, 0, 0).is_err());
+    assert_eq!(
+        SlidingSavGol::new(5, 4, 0).unwrap_err(),
+        "order must be at most 3"
+    );
+    assert!(SlidingSavGol::new(1, 3, 0).is_err());
+    let filter = SlidingSavGol::new(4, 2, 1).unwrap();
+    assert_eq!(
+        (filter.half_width(), filter.order(), filter.deriv()),
+        (4, 2, 1)
+    );
+}
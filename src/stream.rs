@@ -0,0 +1,214 @@
+//! Smoothing of async streams of chunks, behind the `stream` feature.
+//!
+//! `SavGolStreamExt` adds `.sav_gol::<WINDOW, M>()` to every `futures::Stream<Item = Vec<f64>>`.
+//! Chunks can have any size: the last `2 * WINDOW + 1` samples are carried over to the next
+//! chunk, so the concatenated output is the same as a single `sav_gol` over the whole signal,
+//! with the fringes handled like in `iter`.
+
+use crate::boundary::Mode;
+use crate::iter::{fringe_values, short_values, Fringes};
+use crate::sav_gol;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures_core::Stream;
+
+/// Stream returned by `SavGolStreamExt::sav_gol`.
+#[derive(Debug, Clone)]
+pub struct SavGolStream<S, const WINDOW: usize, const M: usize> {
+    stream: S,
+    fringes: Fringes,
+    /// The last samples seen, `2 * WINDOW + 1` of them once the first window is full
+    carry: Vec<f64>,
+    started: bool,
+    done: bool,
+}
+
+pub trait SavGolStreamExt: Stream<Item = Vec<f64>> + Sized {
+    /// Smooths the chunks with the coefficients of `sav_gol::<WINDOW, M>`, filtering the fringes
+    /// with `Mode::Interp` unless told otherwise with `SavGolStream::fringes`. The stream must be
+    /// `Unpin`, `Box::pin` it otherwise.
+    /// ```
+    ///     use futures::{executor::block_on, stream, StreamExt};
+    ///     use staged_sg_filter::stream::SavGolStreamExt;
+    ///     let chunks = stream::iter(vec![vec![0.0, 1.0], vec![4.0, 9.0, 16.0], vec![25.0]]);
+    ///     let smooth: Vec<f64> = block_on(chunks.sav_gol::<2, 2>().concat());
+    ///     assert!(smooth.iter().enumerate().all(|(i, y)| (y - (i * i) as f64).abs() < 1e-9));
+    ///```
+    fn sav_gol<const WINDOW: usize, const M: usize>(self) -> SavGolStream<Self, WINDOW, M> {
+        SavGolStream {
+            stream: self,
+            fringes: Fringes::Filter(Mode::Interp),
+            carry: Vec::with_capacity(2 * WINDOW + 1),
+            started: false,
+            done: false,
+        }
+    }
+}
+
+impl<S: Stream<Item = Vec<f64>>> SavGolStreamExt for S {}
+
+impl<S, const WINDOW: usize, const M: usize> SavGolStream<S, WINDOW, M> {
+    /// Sets how the fringes are handled, before polling.
+    pub fn fringes(mut self, fringes: Fringes) -> Self {
+        if fringes == Fringes::Filter(Mode::Wrap) {
+            panic!("Mode::Wrap can't be used on a stream");
+        }
+        if self.started || !self.carry.is_empty() {
+            panic!("fringes must be set before polling");
+        }
+        self.fringes = fringes;
+        self
+    }
+
+    /// Smooths the carried samples followed by `chunk`, returning the values not yielded yet
+    fn process(&mut self, chunk: Vec<f64>) -> Vec<f64> {
+        let window_size = 2 * WINDOW + 1;
+        self.carry.extend(chunk);
+        let data = &self.carry;
+        if data.len() < window_size {
+            return vec![];
+        }
+        let mut out = vec![];
+        // The center of the first carried window was yielded with the previous chunk
+        let first = match self.started {
+            true => WINDOW + 1,
+            false => {
+                out = fringe_values::<WINDOW, M>(self.fringes, &data[..window_size], true);
+                WINDOW
+            }
+        };
+        let mut buf = vec![0.0; data.len()];
+        sav_gol::<WINDOW, M>(&mut buf, data);
+        out.extend(&buf[first..data.len() - WINDOW]);
+        self.carry.drain(..data.len() - window_size);
+        self.started = true;
+        out
+    }
+
+    /// Whatever is left once the input is over
+    fn finish(&mut self) -> Vec<f64> {
+        match self.started {
+            true => fringe_values::<WINDOW, M>(self.fringes, &self.carry, false),
+            // The stream ended before filling a window
+            false => short_values::<M>(self.fringes, &self.carry),
+        }
+    }
+}
+
+impl<S: Stream<Item = Vec<f64>> + Unpin, const WINDOW: usize, const M: usize> Stream
+    for SavGolStream<S, WINDOW, M>
+{
+    type Item = Vec<f64>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Vec<f64>>> {
+        // Keep polling until some output is ready, chunks smaller than the window may not yield any
+        while !self.done {
+            match Pin::new(&mut self.stream).poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(chunk)) => {
+                    let out = self.process(chunk);
+                    if !out.is_empty() {
+                        return Poll::Ready(Some(out));
+                    }
+                }
+                Poll::Ready(None) => {
+                    self.done = true;
+                    let out = self.finish();
+                    if !out.is_empty() {
+                        return Poll::Ready(Some(out));
+                    }
+                }
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+#[test]
+fn test_sav_gol_stream() {
+    use crate::boundary::fill_fringes;
+    use futures::{channel::mpsc, executor::block_on, StreamExt};
+    let v: Vec<f64> = (0..60)
+        .map(|i| (i as f64 * 0.3).cos() * 2.0 + (i % 4) as f64)
+        .collect();
+    let mut ans = v.clone();
+    sav_gol::<4, 3>(&mut ans, &v);
+    let mut filtered = ans.clone();
+    fill_fringes(&mut filtered, &v, 4, 3, 0, Mode::Mirror);
+
+    for sizes in [
+        vec![60],
+        vec![1],
+        vec![3, 17, 1, 2],
+        vec![9, 0, 5],
+        vec![25, 30],
+    ] {
+        // Cycle through the chunk sizes to cover the whole signal
+        let chunks = || {
+            let (tx, rx) = mpsc::unbounded();
+            let mut start = 0;
+            for &size in sizes.iter().cycle() {
+                let end = (start + size).min(v.len());
+                tx.unbounded_send(v[start..end].to_vec()).unwrap();
+                start = end;
+                if start == v.len() {
+                    break;
+                }
+            }
+            rx
+        };
+        let raw: Vec<f64> = block_on(chunks().sav_gol::<4, 3>().fringes(Fringes::Raw).concat());
+        assert_eq!(raw, ans, "{sizes:?}");
+        let skip: Vec<f64> = block_on(chunks().sav_gol::<4, 3>().fringes(Fringes::Skip).concat());
+        assert_eq!(skip, ans[4..56], "{sizes:?}");
+        let res: Vec<f64> = block_on(
+            chunks()
+                .sav_gol::<4, 3>()
+                .fringes(Fringes::Filter(Mode::Mirror))
+                .concat(),
+        );
+        assert_eq!(res.len(), 60);
+        res.iter()
+            .zip(filtered.iter())
+            .for_each(|(r, a)| assert!((r - a).abs() < 1e-12, "{sizes:?}"));
+    }
+}
+
+#[test]
+fn test_sav_gol_stream_short() {
+    use futures::{executor::block_on, stream, StreamExt};
+    // 4 samples of a parabola in 2 chunks, fewer than the window of 7
+    let chunks = || stream::iter(vec![vec![0.0, 1.0], vec![], vec![4.0, 9.0]]);
+    let res: Vec<f64> = block_on(chunks().sav_gol::<3, 2>().concat());
+    assert_eq!(res.len(), 4);
+    res.iter()
+        .zip([0.0, 1.0, 4.0, 9.0])
+        .for_each(|(r, a)| assert!((r - a).abs() < 1e-12));
+    let raw: Vec<f64> = block_on(chunks().sav_gol::<3, 2>().fringes(Fringes::Raw).concat());
+    assert_eq!(raw, vec![0.0, 1.0, 4.0, 9.0]);
+    let skip: Vec<Vec<f64>> = block_on(chunks().sav_gol::<3, 2>().fringes(Fringes::Skip).collect());
+    assert!(skip.is_empty());
+    let empty = stream::iter(Vec::<Vec<f64>>::new());
+    assert!(block_on(empty.sav_gol::<3, 2>().collect::<Vec<_>>()).is_empty());
+}
+
+#[test]
+fn test_sav_gol_stream_pending() {
+    use futures::{channel::mpsc, executor::block_on, StreamExt};
+    // Chunks arrive from another thread while the stream is being polled
+    let (mut tx, rx) = mpsc::channel(1);
+    let sender = std::thread::spawn(move || {
+        block_on(async {
+            for i in 0..10 {
+                futures::SinkExt::send(&mut tx, vec![i as f64; 3])
+                    .await
+                    .unwrap();
+            }
+        })
+    });
+    let out: Vec<Vec<f64>> = block_on(rx.sav_gol::<2, 2>().fringes(Fringes::Raw).collect());
+    sender.join().unwrap();
+    assert_eq!(out.iter().map(|c| c.len()).sum::<usize>(), 30);
+    // No output until the first window of 5 is full
+    assert_eq!(out[0].len(), 4);
+}
@@ -0,0 +1,45 @@
+//! Deterministic inputs shared by the tests.
+
+// Which helpers are used depends on the enabled features
+#![allow(dead_code)]
+
+/// xorshift64 generator, so that test inputs are reproducible without a dependency
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    pub(crate) fn new(seed: u64) -> Self {
+        XorShift(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Uniform in `-0.5..0.5`
+    pub(crate) fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 - 0.5
+    }
+}
+
+/// `n` samples of uniform noise in `-0.5..0.5`
+pub(crate) fn noise(seed: u64, n: usize) -> Vec<f64> {
+    let mut rng = XorShift::new(seed);
+    (0..n).map(|_| rng.uniform()).collect()
+}
+
+/// `n` samples of a sine of `amplitude` advancing `step` radians per sample, plus a pattern
+/// cycling through `period` levels `scale` apart in a noise-like order
+pub(crate) fn noisy_sine(
+    n: usize,
+    step: f64,
+    amplitude: f64,
+    period: usize,
+    scale: f64,
+) -> Vec<f64> {
+    (0..n)
+        .map(|i| (i as f64 * step).sin() * amplitude + ((i * 7919) % period) as f64 * scale)
+        .collect()
+}
@@ -0,0 +1,418 @@
+//! Dependency-free reading and writing of WAV files.
+//!
+//! PCM with 16, 24 or 32-bit integer samples and IEEE float with 32 or 64-bit samples are
+//! supported, both in the plain and the `WAVE_FORMAT_EXTENSIBLE` flavors, with any number of
+//! interleaved channels. Samples are held as `f32` in `[-1.0, 1.0)` so they can go straight
+//! through `sav_gol_interleaved_f32`, and are quantized back to their original format on writing.
+
+use std::io::{self, Read, Write};
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+/// Tail shared by the `KSDATAFORMAT_SUBTYPE_*` GUIDs, after the format tag in the first 2 bytes
+const SUBTYPE_TAIL: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+
+/// How samples are stored in the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+    Int16,
+    Int24,
+    Int32,
+    Float32,
+    Float64,
+}
+
+impl SampleFormat {
+    pub fn bits(self) -> u16 {
+        match self {
+            SampleFormat::Int16 => 16,
+            SampleFormat::Int24 => 24,
+            SampleFormat::Int32 | SampleFormat::Float32 => 32,
+            SampleFormat::Float64 => 64,
+        }
+    }
+
+    fn bytes(self) -> usize {
+        self.bits() as usize / 8
+    }
+
+    fn is_float(self) -> bool {
+        matches!(self, SampleFormat::Float32 | SampleFormat::Float64)
+    }
+}
+
+/// Interleaved audio, `samples[frame * channels + channel]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wav {
+    pub channels: usize,
+    pub sample_rate: u32,
+    pub format: SampleFormat,
+    pub samples: Vec<f32>,
+}
+
+impl Wav {
+    /// Number of samples per channel
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn u16_at(bytes: &[u8], i: usize) -> u16 {
+    u16::from_le_bytes([bytes[i], bytes[i + 1]])
+}
+
+fn u32_at(bytes: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap())
+}
+
+/// Reads the `len` byte body of a chunk. `len` comes from the file, so the buffer only grows as
+/// data arrives instead of being allocated up front
+fn read_chunk(r: &mut impl Read, len: usize) -> io::Result<Vec<u8>> {
+    let mut body = vec![];
+    r.take(len as u64).read_to_end(&mut body)?;
+    if body.len() < len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("wav chunk ends after {} of {len} bytes", body.len()),
+        ));
+    }
+    Ok(body)
+}
+
+/// Parses the body of a `fmt ` chunk into the channel count, sample rate and sample format
+fn parse_fmt(fmt: &[u8]) -> io::Result<(usize, u32, SampleFormat)> {
+    if fmt.len() < 16 {
+        return Err(invalid("wav fmt chunk is too short"));
+    }
+    let mut tag = u16_at(fmt, 0);
+    let channels = u16_at(fmt, 2) as usize;
+    let sample_rate = u32_at(fmt, 4);
+    let bits = u16_at(fmt, 14);
+    if tag == WAVE_FORMAT_EXTENSIBLE {
+        if fmt.len() < 40 || fmt[26..40] != SUBTYPE_TAIL {
+            return Err(invalid("unsupported WAVE_FORMAT_EXTENSIBLE subformat"));
+        }
+        tag = u16_at(fmt, 24);
+    }
+    let format = match (tag, bits) {
+        (WAVE_FORMAT_PCM, 16) => SampleFormat::Int16,
+        (WAVE_FORMAT_PCM, 24) => SampleFormat::Int24,
+        (WAVE_FORMAT_PCM, 32) => SampleFormat::Int32,
+        (WAVE_FORMAT_IEEE_FLOAT, 32) => SampleFormat::Float32,
+        (WAVE_FORMAT_IEEE_FLOAT, 64) => SampleFormat::Float64,
+        (WAVE_FORMAT_PCM, _) => {
+            return Err(invalid(format!(
+                "unsupported {bits}-bit PCM, expected 16, 24 or 32 bits"
+            )))
+        }
+        (WAVE_FORMAT_IEEE_FLOAT, _) => {
+            return Err(invalid(format!(
+                "unsupported {bits}-bit float, expected 32 or 64 bits"
+            )))
+        }
+        _ => return Err(invalid(format!("unsupported wav format tag {tag:#06x}"))),
+    };
+    if channels == 0 {
+        return Err(invalid("wav file has no channels"));
+    }
+    if u16_at(fmt, 12) as usize != channels * format.bytes() {
+        return Err(invalid("wav block align doesn't match the sample format"));
+    }
+    Ok((channels, sample_rate, format))
+}
+
+fn decode(bytes: &[u8], format: SampleFormat) -> Vec<f32> {
+    let chunks = bytes.chunks_exact(format.bytes());
+    match format {
+        SampleFormat::Int16 => chunks
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect(),
+        // Shift the 3 bytes into the top of an `i32` to sign extend them
+        SampleFormat::Int24 => chunks
+            .map(|b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8388608.0)
+            .collect(),
+        SampleFormat::Int32 => chunks
+            .map(|b| (i32::from_le_bytes(b.try_into().unwrap()) as f64 / 2147483648.0) as f32)
+            .collect(),
+        SampleFormat::Float32 => chunks
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect(),
+        SampleFormat::Float64 => chunks
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()) as f32)
+            .collect(),
+    }
+}
+
+/// Reads a WAV file, converting its samples to `f32`. Chunks other than `fmt ` and `data` are
+/// skipped.
+pub fn read_wav(mut r: impl Read) -> io::Result<Wav> {
+    let mut riff = [0u8; 12];
+    r.read_exact(&mut riff)?;
+    if &riff[..4] != b"RIFF" || &riff[8..] != b"WAVE" {
+        return Err(invalid("not a wav file"));
+    }
+    let mut fmt = None;
+    loop {
+        let mut header = [0u8; 8];
+        r.read_exact(&mut header).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => invalid("wav file has no data chunk"),
+            _ => e,
+        })?;
+        let len = u32_at(&header, 4) as usize;
+        match &header[..4] {
+            b"fmt " => {
+                fmt = Some(parse_fmt(&read_chunk(&mut r, len)?)?);
+            }
+            b"data" => {
+                let (channels, sample_rate, format) =
+                    fmt.ok_or_else(|| invalid("wav data chunk comes before the fmt chunk"))?;
+                let body = read_chunk(&mut r, len)?;
+                if !len.is_multiple_of(channels * format.bytes()) {
+                    return Err(invalid("wav data chunk ends in the middle of a frame"));
+                }
+                return Ok(Wav {
+                    channels,
+                    sample_rate,
+                    format,
+                    samples: decode(&body, format),
+                });
+            }
+            _ => {
+                io::copy(&mut (&mut r).take(len as u64), &mut io::sink())?;
+            }
+        }
+        // Chunks are padded to an even length
+        if len % 2 == 1 {
+            r.read_exact(&mut [0u8; 1])?;
+        }
+    }
+}
+
+/// Triangular dither of +/-1 LSB from a fixed seed, so that output files are reproducible
+struct Dither(u64);
+
+impl Dither {
+    fn uniform(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn next(&mut self) -> f64 {
+        self.uniform() - self.uniform()
+    }
+}
+
+/// Writes `wav` with samples quantized back to `wav.format`.
+///
+/// Integer formats are rounded with triangular dither if `dither` is set, and clamped to their
+/// range either way. Files with more than 2 channels or more than 16 bits are written as
+/// `WAVE_FORMAT_EXTENSIBLE`, as the WAV spec asks.
+pub fn write_wav(mut w: impl Write, wav: &Wav, dither: bool) -> io::Result<()> {
+    if wav.channels == 0 || !wav.samples.len().is_multiple_of(wav.channels) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "wav samples must hold a whole number of frames",
+        ));
+    }
+    let format = wav.format;
+    let tag = match format.is_float() {
+        true => WAVE_FORMAT_IEEE_FLOAT,
+        false => WAVE_FORMAT_PCM,
+    };
+    let too_large = |what: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("wav {what} doesn't fit in the header"),
+        )
+    };
+    let extensible = wav.channels > 2 || format.bits() > 16;
+    let channels = u16::try_from(wav.channels).map_err(|_| too_large("channel count"))?;
+    let block_align = wav
+        .channels
+        .checked_mul(format.bytes())
+        .and_then(|n| u16::try_from(n).ok())
+        .ok_or_else(|| too_large("frame size"))?;
+    let byte_rate = wav
+        .sample_rate
+        .checked_mul(block_align as u32)
+        .ok_or_else(|| too_large("byte rate"))?;
+    let data_len = wav
+        .samples
+        .len()
+        .checked_mul(format.bytes())
+        .and_then(|n| u32::try_from(n).ok())
+        .ok_or_else(|| too_large("data size"))?;
+    let mut fmt = vec![];
+    fmt.extend(
+        match extensible {
+            true => WAVE_FORMAT_EXTENSIBLE,
+            false => tag,
+        }
+        .to_le_bytes(),
+    );
+    fmt.extend(channels.to_le_bytes());
+    fmt.extend(wav.sample_rate.to_le_bytes());
+    fmt.extend(byte_rate.to_le_bytes());
+    fmt.extend(block_align.to_le_bytes());
+    fmt.extend(format.bits().to_le_bytes());
+    if extensible {
+        fmt.extend(22u16.to_le_bytes());
+        fmt.extend(format.bits().to_le_bytes());
+        // No particular speaker layout
+        fmt.extend(0u32.to_le_bytes());
+        fmt.extend(tag.to_le_bytes());
+        fmt.extend(SUBTYPE_TAIL);
+    }
+
+    // The RIFF length counts the fmt chunk (at most 40 bytes) and the padding of the data
+    let riff_len = (4 + 8 + fmt.len() as u32 + 8)
+        .checked_add(data_len + data_len % 2)
+        .ok_or_else(|| too_large("data size"))?;
+    let mut data = Vec::with_capacity(data_len as usize);
+    let mut noise = Dither(0x9E37_79B9_7F4A_7C15);
+    let mut quantize = |x: f32, bits: u32| -> i32 {
+        let scale = (1u64 << (bits - 1)) as f64;
+        let d = if dither { noise.next() } else { 0.0 };
+        (x as f64 * scale + d).round().clamp(-scale, scale - 1.0) as i32
+    };
+    wav.samples.iter().for_each(|&x| match format {
+        SampleFormat::Int16 => data.extend((quantize(x, 16) as i16).to_le_bytes()),
+        SampleFormat::Int24 => data.extend(&quantize(x, 24).to_le_bytes()[..3]),
+        SampleFormat::Int32 => data.extend(quantize(x, 32).to_le_bytes()),
+        SampleFormat::Float32 => data.extend(x.to_le_bytes()),
+        SampleFormat::Float64 => data.extend((x as f64).to_le_bytes()),
+    });
+
+    w.write_all(b"RIFF")?;
+    w.write_all(&riff_len.to_le_bytes())?;
+    w.write_all(b"WAVE")?;
+    w.write_all(b"fmt ")?;
+    w.write_all(&(fmt.len() as u32).to_le_bytes())?;
+    w.write_all(&fmt)?;
+    w.write_all(b"data")?;
+    w.write_all(&data_len.to_le_bytes())?;
+    w.write_all(&data)?;
+    if data.len() % 2 == 1 {
+        w.write_all(&[0])?;
+    }
+    w.flush()
+}
+
+#[test]
+fn test_wav_round_trip() {
+    let samples: Vec<f32> = (0..30).map(|i| (i as f32 * 0.4).sin() * 0.9).collect();
+    for format in [
+        SampleFormat::Int16,
+        SampleFormat::Int24,
+        SampleFormat::Int32,
+        SampleFormat::Float32,
+        SampleFormat::Float64,
+    ] {
+        for channels in [1, 3] {
+            let wav = Wav {
+                channels,
+                sample_rate: 48000,
+                format,
+                samples: samples.clone(),
+            };
+            let mut bytes = vec![];
+            write_wav(&mut bytes, &wav, false).unwrap();
+            let once = read_wav(bytes.as_slice()).unwrap();
+            assert_eq!(once.channels, channels);
+            assert_eq!(once.sample_rate, 48000);
+            assert_eq!(once.format, format);
+            let lsb = 1.0 / (1u64 << (format.bits() - 1)) as f32;
+            once.samples
+                .iter()
+                .zip(samples.iter())
+                .for_each(|(a, b)| assert!((a - b).abs() <= lsb.max(1e-7), "{format:?}"));
+            // Samples that are already quantized come back unchanged
+            let mut again = vec![];
+            write_wav(&mut again, &once, false).unwrap();
+            assert_eq!(again, bytes);
+        }
+    }
+}
+
+#[test]
+fn test_wav_dither_clamp() {
+    let wav = Wav {
+        channels: 1,
+        sample_rate: 8000,
+        format: SampleFormat::Int16,
+        samples: vec![0.25; 1000].into_iter().chain([1.5, -1.5]).collect(),
+    };
+    let mut bytes = vec![];
+    write_wav(&mut bytes, &wav, true).unwrap();
+    let res = read_wav(bytes.as_slice()).unwrap().samples;
+    // Within 1 LSB, and not biased on average
+    let lsb = 1.0 / 32768.0;
+    assert!(res[..1000].iter().all(|x| (x - 0.25).abs() <= lsb));
+    let mean = res[..1000].iter().sum::<f32>() / 1000.0;
+    assert!((mean - 0.25).abs() < 0.1 * lsb);
+    assert_eq!(res[1000], 32767.0 / 32768.0);
+    assert_eq!(res[1001], -1.0);
+}
+
+#[test]
+fn test_read_wav() {
+    // A 16-bit mono file with a `LIST` chunk of odd length before the data
+    let mut bytes = b"RIFF\x00\x00\x00\x00WAVEfmt \x10\x00\x00\x00".to_vec();
+    bytes.extend([1, 0, 1, 0, 0x44, 0xAC, 0, 0, 0x88, 0x58, 1, 0, 2, 0, 16, 0]);
+    bytes.extend(b"LIST\x03\x00\x00\x00abc\x00");
+    bytes.extend(b"data\x04\x00\x00\x00");
+    bytes.extend([0x00, 0x40, 0x00, 0xC0]);
+    let wav = read_wav(bytes.as_slice()).unwrap();
+    assert_eq!(wav.sample_rate, 44100);
+    assert_eq!(wav.format, SampleFormat::Int16);
+    assert_eq!(wav.samples, vec![0.5, -0.5]);
+    assert_eq!(wav.frames(), 2);
+
+    let mut bad = bytes.clone();
+    bad[34] = 8;
+    assert!(read_wav(bad.as_slice())
+        .unwrap_err()
+        .to_string()
+        .contains("8-bit PCM"));
+    assert!(read_wav(&bytes[..40]).is_err());
+    assert!(read_wav(&b"RIFX\x00\x00\x00\x00WAVE"[..]).is_err());
+
+    // Chunk lengths near 4 GiB that the file doesn't back fail without allocating them
+    let mut hostile = b"RIFF\x00\x00\x00\x00WAVEfmt \xF0\xFF\xFF\xFF".to_vec();
+    hostile.extend([0u8; 16]);
+    let err = read_wav(hostile.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert!(err.to_string().contains("after 16 of 4294967280 bytes"));
+    let mut hostile = bytes[..36].to_vec();
+    hostile.extend(b"data\xFE\xFF\xFF\xFF\x00\x40");
+    assert_eq!(
+        read_wav(hostile.as_slice()).unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+}
+
+#[test]
+fn test_write_wav_too_large() {
+    let err = |channels: usize, sample_rate: u32| {
+        let wav = Wav {
+            channels,
+            sample_rate,
+            format: SampleFormat::Float64,
+            samples: vec![0.0; channels],
+        };
+        write_wav(&mut vec![], &wav, false).unwrap_err()
+    };
+    assert_eq!(err(2, u32::MAX).kind(), io::ErrorKind::InvalidInput);
+    assert!(err(2, u32::MAX).to_string().contains("byte rate"));
+    assert!(err(70000, 48000).to_string().contains("channel count"));
+    assert!(err(9000, 48000).to_string().contains("frame size"));
+}